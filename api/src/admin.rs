@@ -0,0 +1,96 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::IntoResponse,
+};
+use bincode::config::Configuration;
+use tasks_tracker_common::{unix_timestamp, AbortFilter, AbortResult, TaskStatus, TaskUpdate};
+use tracing::info;
+use uuid::Uuid;
+
+use crate::{apply_update, authorize::is_authorized, labels::Selector, AppState, ClientPrivilege};
+
+/// abort all the unfinished tasks matching a filter, with the admin token.
+// returns the ids of the matching tasks, split between the aborted ones and the ones which finished in the meantime.
+pub(crate) async fn abort_tasks(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    body: Bytes,
+) -> impl IntoResponse {
+    let authorized_status = is_authorized(&headers, &state, ClientPrivilege::Admin);
+    if authorized_status != StatusCode::OK {
+        return authorized_status.into_response();
+    }
+    let Ok((filter, _)) =
+        bincode::decode_from_slice::<AbortFilter, Configuration>(&body, state.config_bincode)
+    else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let Some(selector) = Selector::parse(filter.labels.as_deref().unwrap_or_default()) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let now = unix_timestamp();
+    let ids = state
+        .store
+        .lock()
+        .unwrap()
+        .tasks
+        .iter()
+        .filter(|t| t.status == TaskStatus::Active || t.status == TaskStatus::Blocked)
+        .filter(|t| filter.scope.as_ref().is_none_or(|scope| &t.scope == scope))
+        .filter(|t| {
+            filter
+                .status
+                .as_ref()
+                .is_none_or(|status| &t.status == status)
+        })
+        .filter(|t| {
+            filter
+                .older_than
+                .is_none_or(|age| t.created_at.saturating_add(age) <= now)
+        })
+        .filter(|t| selector.matches(&t.labels))
+        .map(|t| t.id)
+        .collect::<Vec<Uuid>>();
+    let mut result = AbortResult {
+        matched: ids,
+        aborted: Vec::new(),
+        failed: Vec::new(),
+    };
+    if !filter.dry_run {
+        // tasks are aborted one by one like with the abort token, so listeners are notified.
+        // a task can finish between the match and its abort, it is then reported as failed.
+        for id in result.matched.iter() {
+            let response = apply_update(
+                &state,
+                &HeaderMap::new(),
+                *id,
+                TaskUpdate::finish(
+                    TaskStatus::Aborted,
+                    filter.description_result.clone(),
+                    Vec::new(),
+                ),
+                true,
+            );
+            if response.status() == StatusCode::ACCEPTED {
+                result.aborted.push(*id);
+            } else {
+                result.failed.push(*id);
+            }
+        }
+    }
+    info!(
+        target: "audit",
+        action = "bulk_abort",
+        matched = result.matched.len(),
+        aborted = result.aborted.len(),
+        failed = result.failed.len(),
+        dry_run = filter.dry_run,
+        scope = filter.scope,
+        labels = filter.labels
+    );
+    bincode::encode_to_vec(result, state.config_bincode)
+        .unwrap()
+        .into_response()
+}
@@ -0,0 +1,188 @@
+use std::{
+    cell::Cell,
+    collections::{HashMap, VecDeque},
+    net::SocketAddr,
+};
+
+use axum::{
+    extract::{ConnectInfo, Path, Request, State},
+    http::{HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use tasks_tracker_common::{unix_timestamp, AuditEntry};
+use uuid::Uuid;
+
+use crate::{authorize::is_authorized, AppState, ClientPrivilege};
+
+// maximum number of audit entries kept for a task, older entries are discarded.
+const AUDIT_LEN: usize = 256;
+
+// who is making the current request, changes of tasks made outside of a request are attributed to the system.
+#[derive(Clone, Copy)]
+struct Actor {
+    privilege: &'static str,
+    ip: Option<SocketAddr>,
+}
+
+tokio::task_local! {
+    static ACTOR: Cell<Actor>;
+}
+
+/// middleware remembering the client address of the request for the audit trail.
+pub(crate) async fn track_actor(request: Request, next: Next) -> Response {
+    let actor = Actor {
+        privilege: "none",
+        ip: request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|info| info.0),
+    };
+    ACTOR.scope(Cell::new(actor), next.run(request)).await
+}
+
+// remember the privilege granted to the current request.
+pub(crate) fn record_privilege(privilege: &'static str) {
+    let _ = ACTOR.try_with(|actor| {
+        actor.set(Actor {
+            privilege,
+            ..actor.get()
+        })
+    });
+}
+
+/// audit entries of every task, retained as long as the task.
+#[derive(Default)]
+pub(crate) struct AuditTrail(HashMap<Uuid, VecDeque<AuditEntry>>);
+
+impl AuditTrail {
+    // record a change of a task made by the current request.
+    pub(crate) fn record(
+        &mut self,
+        uuid: Uuid,
+        action: &str,
+        old_value: String,
+        new_value: String,
+    ) {
+        let actor = ACTOR.try_with(Cell::get).ok();
+        self.push(uuid, actor, action, old_value, new_value);
+    }
+    // record a change of a task made by the server itself, even while handling a request changing another task.
+    pub(crate) fn record_system(
+        &mut self,
+        uuid: Uuid,
+        action: &str,
+        old_value: String,
+        new_value: String,
+    ) {
+        self.push(uuid, None, action, old_value, new_value);
+    }
+    fn push(
+        &mut self,
+        uuid: Uuid,
+        actor: Option<Actor>,
+        action: &str,
+        old_value: String,
+        new_value: String,
+    ) {
+        let entries = self.0.entry(uuid).or_default();
+        if entries.len() >= AUDIT_LEN {
+            entries.pop_front();
+        }
+        entries.push_back(AuditEntry {
+            timestamp: unix_timestamp(),
+            privilege: actor.map_or("system", |a| a.privilege).to_string(),
+            client_ip: actor.and_then(|a| a.ip).map(|ip| ip.ip().to_string()),
+            action: action.to_string(),
+            old_value,
+            new_value,
+        });
+    }
+    pub(crate) fn entries(&self, uuid: &Uuid) -> Vec<AuditEntry> {
+        self.0
+            .get(uuid)
+            .map(|entries| entries.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+    pub(crate) fn remove(&mut self, uuid: &Uuid) {
+        self.0.remove(uuid);
+    }
+}
+
+/// read the audit trail of a task, with the update or admin token.
+pub(crate) async fn read_audit(
+    headers: HeaderMap,
+    Path(uuid): Path<Uuid>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let authorized_status = is_authorized(&headers, &state, ClientPrivilege::Update(uuid));
+    if authorized_status != StatusCode::OK {
+        return authorized_status.into_response();
+    }
+    let store = state.store.lock().unwrap();
+    if store.get(&uuid).is_none() {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    bincode::encode_to_vec(store.audit.entries(&uuid), state.config_bincode)
+        .unwrap()
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use tasks_tracker_common::{NewTask, Task, TaskStatus};
+
+    use super::*;
+    use crate::{dependencies::resolve_dependents, store::Store};
+
+    fn task(dependencies: Vec<Uuid>) -> Task {
+        NewTask {
+            dependencies,
+            ..NewTask::default()
+        }
+        .to_task()
+    }
+
+    #[tokio::test]
+    async fn record_attributes_the_change_to_the_request() {
+        let actor = Actor {
+            privilege: "update",
+            ip: Some(SocketAddr::from(([192, 0, 2, 1], 4000))),
+        };
+        let mut audit = AuditTrail::default();
+        let uuid = Uuid::new_v4();
+        ACTOR
+            .scope(Cell::new(actor), async {
+                audit.record(uuid, "progress", "0".to_string(), "50".to_string())
+            })
+            .await;
+        let entries = audit.entries(&uuid);
+        assert_eq!(entries[0].privilege, "update");
+        assert_eq!(entries[0].client_ip.as_deref(), Some("192.0.2.1"));
+    }
+
+    #[tokio::test]
+    async fn cascade_is_attributed_to_the_system() {
+        let actor = Actor {
+            privilege: "update",
+            ip: Some(SocketAddr::from(([192, 0, 2, 1], 4000))),
+        };
+        let mut store = Store::default();
+        let aborted = task(Vec::new());
+        let dependent = task(vec![aborted.id]);
+        let (aborted_id, dependent_id) = (aborted.id, dependent.id);
+        store.insert(aborted);
+        store.insert(dependent);
+        store.get_mut(&dependent_id).unwrap().status = TaskStatus::Blocked;
+        ACTOR
+            .scope(Cell::new(actor), async {
+                store.get_mut(&aborted_id).unwrap().status = TaskStatus::Aborted;
+                resolve_dependents(&mut store, aborted_id);
+            })
+            .await;
+        let entries = store.audit.entries(&dependent_id);
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].privilege, "system");
+        assert_eq!(entries[0].client_ip, None);
+    }
+}
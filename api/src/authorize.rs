@@ -1,7 +1,8 @@
-use crate::{AppState, ClientPrivilege};
+use crate::{audit::record_privilege, metrics::METRICS, AppState, ClientPrivilege};
 use axum::http::{header::AUTHORIZATION, HeaderMap};
 use reqwest::StatusCode;
 use tasks_tracker_common::Task;
+use tracing::Span;
 use uuid::Uuid;
 
 /// functions for managing grant access to endpoints.
@@ -10,6 +11,38 @@ pub(crate) fn is_authorized(
     headers: &HeaderMap,
     state: &AppState,
     privilege_required: ClientPrivilege,
+) -> StatusCode {
+    let privilege = privilege_required.name();
+    let status = authorization_status(headers, state, privilege_required);
+    if status != StatusCode::OK {
+        METRICS.auth_failure(status);
+        return status;
+    }
+    // the privilege used is recorded on the span of the request for the logs.
+    let admin = state.token_admin.as_ref().is_some_and(|admin| {
+        headers
+            .get(AUTHORIZATION)
+            .is_some_and(|token| token == ["Bearer ", admin].concat().as_str())
+    });
+    let privilege = if admin { "admin" } else { privilege };
+    Span::current().record("privilege", privilege);
+    record_privilege(privilege);
+    status
+}
+
+// whether the request holds a privilege, without counting a refusal, to decide what a response reveals.
+pub(crate) fn has_privilege(
+    headers: &HeaderMap,
+    state: &AppState,
+    privilege: ClientPrivilege,
+) -> bool {
+    authorization_status(headers, state, privilege) == StatusCode::OK
+}
+
+fn authorization_status(
+    headers: &HeaderMap,
+    state: &AppState,
+    privilege_required: ClientPrivilege,
 ) -> StatusCode {
     if let Some(value) = headers.get(AUTHORIZATION) {
         if let Ok(token) = value.to_str() {
@@ -40,15 +73,16 @@ pub(crate) fn is_authorized(
                             None
                         }
                     }
-                    ClientPrivilege::List => state.token_admin.clone(),
+                    ClientPrivilege::List | ClientPrivilege::Admin => state.token_admin.clone(),
                 }
                 .is_some_and(|valid_token| ["Bearer ", &valid_token].concat() == token)
             {
                 return StatusCode::OK;
             } else if !state
-                .tasks
+                .store
                 .lock()
                 .unwrap()
+                .tasks
                 .iter()
                 .filter(|t| {
                     ["Bearer ", &t.tokens.0].concat() == token
@@ -74,11 +108,5 @@ pub(crate) fn is_authorized(
 
 // simple function to get the task by his uuid.
 pub(crate) fn task_with_uuid(state: &AppState, uuid: &Uuid) -> Option<Task> {
-    state
-        .tasks
-        .lock()
-        .unwrap()
-        .iter()
-        .find(|t| &t.id == uuid)
-        .cloned()
+    state.store.lock().unwrap().get(uuid).cloned()
 }
@@ -0,0 +1,167 @@
+use axum::{
+    body::Bytes,
+    extract::State,
+    http::{header::AUTHORIZATION, HeaderMap, HeaderValue, StatusCode},
+    response::IntoResponse,
+};
+use bincode::config::Configuration;
+use tasks_tracker_common::{BatchOperation, BatchResult, TaskStatus, TaskUpdate};
+
+use crate::{apply_update, authorize::is_authorized, insert_task, AppState, ClientPrivilege};
+
+/// apply a list of create, update and abort operations in one request.
+// each operation is authorized with its own token and has its own result, a failing operation does not stop the others.
+pub(crate) async fn batch(State(state): State<AppState>, body: Bytes) -> impl IntoResponse {
+    let Ok((operations, _)) = bincode::decode_from_slice::<Vec<BatchOperation>, Configuration>(
+        &body,
+        state.config_bincode,
+    ) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let results = operations
+        .into_iter()
+        .map(|operation| apply_operation(&state, operation))
+        .collect::<Vec<BatchResult>>();
+    bincode::encode_to_vec(results, state.config_bincode)
+        .unwrap()
+        .into_response()
+}
+
+fn apply_operation(state: &AppState, operation: BatchOperation) -> BatchResult {
+    let (token, privilege) = match &operation {
+        BatchOperation::Create { token, .. } => (token, ClientPrivilege::Creation),
+        BatchOperation::Update { token, id, .. } => (token, ClientPrivilege::Update(*id)),
+        BatchOperation::Abort { token, id, .. } => (token, ClientPrivilege::Abort(*id)),
+    };
+    // the token of the operation is authorized like the Authorization header of a single request.
+    let mut headers = HeaderMap::new();
+    match HeaderValue::from_str(&["Bearer ", token].concat()) {
+        Ok(value) => headers.insert(AUTHORIZATION, value),
+        Err(_) => return status_result(StatusCode::BAD_REQUEST),
+    };
+    let authorized_status = is_authorized(&headers, state, privilege);
+    if authorized_status != StatusCode::OK {
+        return status_result(authorized_status);
+    }
+    match operation {
+        BatchOperation::Create {
+            token,
+            new_task,
+            idempotency_key,
+        } => match insert_task(
+            state,
+            &["Bearer ", &token].concat(),
+            idempotency_key.as_deref(),
+            new_task,
+        ) {
+            Ok((status, task)) => BatchResult {
+                status: status.as_u16(),
+                id: Some(task.id),
+                tokens: Some(task.tokens),
+            },
+            Err(status) => status_result(status),
+        },
+        BatchOperation::Update { id, update, .. } => {
+            status_result(apply_update(state, &HeaderMap::new(), id, update, false).status())
+        }
+        BatchOperation::Abort {
+            id,
+            description_result,
+            payload_result,
+            ..
+        } => status_result(
+            apply_update(
+                state,
+                &HeaderMap::new(),
+                id,
+                TaskUpdate::finish(TaskStatus::Aborted, description_result, payload_result),
+                true,
+            )
+            .status(),
+        ),
+    }
+}
+
+fn status_result(status: StatusCode) -> BatchResult {
+    BatchResult {
+        status: status.as_u16(),
+        id: None,
+        tokens: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tasks_tracker_common::{NewTask, BINCODE_CONFIG};
+
+    use super::*;
+    use crate::tests::state;
+
+    #[tokio::test]
+    async fn each_operation_has_its_own_result() {
+        let state = state();
+        let active = NewTask::default().to_task();
+        let mut finished = NewTask::default().to_task();
+        finished.status = TaskStatus::Done;
+        let (active_id, finished_id) = (active.id, finished.id);
+        let (active_tokens, finished_tokens) = (active.tokens.clone(), finished.tokens.clone());
+        state.store.lock().unwrap().insert(active);
+        state.store.lock().unwrap().insert(finished);
+        let operations = vec![
+            BatchOperation::Create {
+                token: "create".to_string(),
+                new_task: NewTask::default(),
+                idempotency_key: None,
+            },
+            BatchOperation::Create {
+                token: "unknown".to_string(),
+                new_task: NewTask::default(),
+                idempotency_key: None,
+            },
+            // the view token does not give the update privilege.
+            BatchOperation::Update {
+                token: active_tokens.0.clone(),
+                id: active_id,
+                update: TaskUpdate::progress(10),
+            },
+            BatchOperation::Update {
+                token: finished_tokens.2.clone(),
+                id: finished_id,
+                update: TaskUpdate::progress(10),
+            },
+            BatchOperation::Update {
+                token: active_tokens.2.clone(),
+                id: active_id,
+                update: TaskUpdate::progress(40),
+            },
+        ];
+        let server = axum_test::TestServer::new(crate::routes(state.clone())).unwrap();
+        let response = server
+            .post("/tasks/batch")
+            .bytes(
+                bincode::encode_to_vec(operations, BINCODE_CONFIG)
+                    .unwrap()
+                    .into(),
+            )
+            .await;
+        response.assert_status_ok();
+        let (results, _) = bincode::decode_from_slice::<Vec<BatchResult>, Configuration>(
+            response.as_bytes(),
+            BINCODE_CONFIG,
+        )
+        .unwrap();
+        let statuses = results.iter().map(|r| r.status).collect::<Vec<u16>>();
+        assert_eq!(statuses, vec![201, 401, 403, 405, 202]);
+        let created = results[0].id.unwrap();
+        let store = state.store.lock().unwrap();
+        assert_eq!(
+            results[0].tokens,
+            store.get(&created).map(|t| t.tokens.clone())
+        );
+        assert!(results[1..]
+            .iter()
+            .all(|r| r.id.is_none() && r.tokens.is_none()));
+        assert_eq!(store.get(&active_id).unwrap().progress, 40);
+        assert_eq!(store.tasks.len(), 3);
+    }
+}
@@ -0,0 +1,182 @@
+use std::{
+    net::SocketAddr,
+    path::{Path, PathBuf},
+};
+
+use clap::{error::ErrorKind, parser::ValueSource, ArgMatches, CommandFactory, FromArgMatches};
+use serde::{Deserialize, Deserializer};
+use tasks_tracker_common::read_secret;
+use tracing_subscriber::EnvFilter;
+
+use crate::{listener, logging::LogFormat, Args};
+
+/// settings of the config file, named like the flags.
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct Config {
+    log_level: Option<String>,
+    log_format: Option<LogFormat>,
+    audit_log: Option<PathBuf>,
+    token_create: Option<String>,
+    token_admin: Option<String>,
+    port: Option<u16>,
+    token_create_file: Option<PathBuf>,
+    token_admin_file: Option<PathBuf>,
+    bind: Option<SocketAddr>,
+    unix_socket: Option<PathBuf>,
+    #[serde(deserialize_with = "octal")]
+    unix_socket_mode: Option<u32>,
+    tls_cert: Option<PathBuf>,
+    tls_key: Option<PathBuf>,
+    shutdown_timeout: Option<u64>,
+    monotonic_progress: Option<bool>,
+    metrics_port: Option<u16>,
+    max_notification_backlog: Option<usize>,
+    notification_timeout: Option<u64>,
+    max_lifetime: Option<u32>,
+    max_duration: Option<u32>,
+    max_body_size: Option<usize>,
+    idempotency_window: Option<u64>,
+}
+
+// permissions are written in octal as a string, like "660".
+fn octal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    let mode = String::deserialize(deserializer)?;
+    listener::parse_mode(&mode)
+        .map(Some)
+        .map_err(serde::de::Error::custom)
+}
+
+// a setting of the config file applies only if it was not given on the command line or in the environment.
+macro_rules! layer {
+    ($args:ident, $matches:ident, $config:ident, $($field:ident),+) => {
+        $(
+            if let Some(value) = $config.$field {
+                if !given(&$matches, stringify!($field)) {
+                    $args.$field = value.into();
+                }
+            }
+        )+
+    };
+}
+
+fn given(matches: &ArgMatches, id: &str) -> bool {
+    matches!(
+        matches.value_source(id),
+        Some(ValueSource::CommandLine | ValueSource::EnvVariable)
+    )
+}
+
+/// parse the arguments completed by the config file, then read the tokens given as files.
+// exits with a usage error if the config file or a token file can not be read, if the listener is
+// ambiguous, if the log level is not a valid filter or if there is no creation token.
+pub(crate) fn load_args() -> Args {
+    let matches = Args::command().get_matches();
+    let mut args = Args::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    if let Some(path) = &args.config {
+        let config: Config = std::fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|content| toml::from_str(&content).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                Args::command()
+                    .error(
+                        ErrorKind::InvalidValue,
+                        format!("invalid config file {}: {}", path.display(), e),
+                    )
+                    .exit()
+            });
+        // a token and its file are alternatives, the file of a higher layer replaces the token of the config file.
+        if !given(&matches, "token_create") && !given(&matches, "token_create_file") {
+            layer!(args, matches, config, token_create, token_create_file);
+        }
+        if !given(&matches, "token_admin") && !given(&matches, "token_admin_file") {
+            layer!(args, matches, config, token_admin, token_admin_file);
+        }
+        layer!(
+            args,
+            matches,
+            config,
+            log_level,
+            log_format,
+            audit_log,
+            port,
+            bind,
+            unix_socket,
+            unix_socket_mode,
+            tls_cert,
+            tls_key,
+            shutdown_timeout,
+            monotonic_progress,
+            metrics_port,
+            max_notification_backlog,
+            notification_timeout,
+            max_lifetime,
+            max_duration,
+            max_body_size,
+            idempotency_window
+        );
+    }
+    check_listener(&args);
+    check_log_level(&args);
+    if let Some(path) = &args.token_create_file {
+        args.token_create = Some(read_token(path));
+    }
+    if let Some(path) = &args.token_admin_file {
+        args.token_admin = Some(read_token(path));
+    }
+    if args.token_create.is_none() {
+        Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "a creation token is required, as argument, in TASKS_TRACKER_TOKEN_CREATE or from a file",
+            )
+            .exit()
+    }
+    args
+}
+
+// checked once all the layers are merged, a flag and a setting of the config file can conflict.
+fn check_listener(args: &Args) {
+    if args.unix_socket.is_some()
+        && (args.bind.is_some() || args.tls_cert.is_some() || args.tls_key.is_some())
+    {
+        Args::command()
+            .error(
+                ErrorKind::ArgumentConflict,
+                "a unix socket can not be used with a bind address or tls",
+            )
+            .exit()
+    }
+    if args.tls_cert.is_some() != args.tls_key.is_some() {
+        Args::command()
+            .error(
+                ErrorKind::MissingRequiredArgument,
+                "tls requires both a certificate and a private key",
+            )
+            .exit()
+    }
+}
+
+// the filter directive is checked once the config file is merged, it can come from any layer.
+fn check_log_level(args: &Args) {
+    if let Err(e) = EnvFilter::try_new(&args.log_level) {
+        Args::command()
+            .error(
+                ErrorKind::InvalidValue,
+                format!("invalid log level {}: {}", args.log_level, e),
+            )
+            .exit()
+    }
+}
+
+// exits with a usage error if the file can not be read.
+fn read_token(path: &Path) -> String {
+    read_secret(path).unwrap_or_else(|e| {
+        Args::command()
+            .error(
+                ErrorKind::InvalidValue,
+                format!("can not read the token file {}: {}", path.display(), e),
+            )
+            .exit()
+    })
+}
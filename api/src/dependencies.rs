@@ -0,0 +1,187 @@
+use axum::http::StatusCode;
+use tasks_tracker_common::{unix_timestamp, Task, TaskStatus};
+use uuid::Uuid;
+
+use crate::{metrics::METRICS, notify::push_notification, store::Store};
+
+/// functions for managing the dependencies between tasks.
+// returns the status a new task must start with, depending on the status of its dependencies.
+// a dependency that does not exist is a bad request, an aborted dependency would make the new task abort immediately so it is refused.
+pub(crate) fn initial_status(
+    tasks: &[Task],
+    dependencies: &[Uuid],
+) -> Result<TaskStatus, StatusCode> {
+    let mut status = TaskStatus::Active;
+    for dependency in dependencies {
+        match tasks.iter().find(|t| &t.id == dependency) {
+            None => return Err(StatusCode::BAD_REQUEST),
+            Some(task) if task.status == TaskStatus::Aborted => return Err(StatusCode::CONFLICT),
+            Some(task) if task.status != TaskStatus::Done => status = TaskStatus::Blocked,
+            Some(_) => {}
+        }
+    }
+    Ok(status)
+}
+
+// must be called when a task is finished, to update the tasks depending on it.
+// blocked tasks are aborted if the dependency was aborted, or become active when all their dependencies are done.
+// these changes are attributed to the system, not to the request which finished the dependency.
+pub(crate) fn resolve_dependents(store: &mut Store, finished: Uuid) {
+    let tasks = &mut store.tasks;
+    let Some(status) = tasks
+        .iter()
+        .find(|t| t.id == finished)
+        .map(|t| t.status.clone())
+    else {
+        return;
+    };
+    let now = unix_timestamp();
+    let mut aborted = Vec::new();
+    let mut unblocked = Vec::new();
+    for i in 0..tasks.len() {
+        if tasks[i].status != TaskStatus::Blocked || !tasks[i].dependencies.contains(&finished) {
+            continue;
+        }
+        if status == TaskStatus::Aborted {
+            let dependent = &mut tasks[i];
+            dependent.status = TaskStatus::Aborted;
+            dependent.touch(now);
+            dependent.finished_at = Some(now);
+            dependent.description_result = format!("dependency {} was aborted", finished);
+            push_notification(dependent);
+            METRICS.finished(&dependent.status);
+            aborted.push(dependent.id);
+        } else if tasks[i].dependencies.iter().all(|dependency| {
+            tasks
+                .iter()
+                .find(|t| &t.id == dependency)
+                .is_none_or(|t| t.status == TaskStatus::Done)
+        }) {
+            // the task is unblocked, listeners are notified that it can start.
+            tasks[i].status = TaskStatus::Active;
+            tasks[i].started_at = Some(now);
+            tasks[i].record_progress(0, now);
+            push_notification(&tasks[i]);
+            unblocked.push(tasks[i].id);
+        }
+    }
+    for id in unblocked {
+        store
+            .audit
+            .record_system(id, "status", "blocked".to_string(), "active".to_string());
+    }
+    // the abortion of a task is cascading to the tasks depending on it.
+    for id in aborted {
+        store.audit.record_system(
+            id,
+            "status",
+            "blocked".to_string(),
+            format!("aborted (dependency {} aborted)", finished),
+        );
+        store.schedule_forget(id);
+        resolve_dependents(store, id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tasks_tracker_common::NewTask;
+
+    use super::*;
+
+    fn task(dependencies: Vec<Uuid>) -> Task {
+        NewTask {
+            dependencies,
+            ..NewTask::default()
+        }
+        .to_task()
+    }
+
+    // insert a task blocked by its dependencies.
+    fn insert_blocked(store: &mut Store, dependencies: Vec<Uuid>) -> Uuid {
+        let mut task = task(dependencies);
+        task.status = TaskStatus::Blocked;
+        task.started_at = None;
+        let uuid = task.id;
+        store.insert(task);
+        uuid
+    }
+
+    fn finish(store: &mut Store, uuid: Uuid, status: TaskStatus) {
+        let task = store.get_mut(&uuid).unwrap();
+        task.status = status;
+        task.finished_at = Some(unix_timestamp());
+        resolve_dependents(store, uuid);
+    }
+
+    fn status(store: &Store, uuid: &Uuid) -> TaskStatus {
+        store.get(uuid).unwrap().status.clone()
+    }
+
+    #[test]
+    fn initial_status_depends_on_the_dependencies() {
+        let active = task(Vec::new());
+        let mut done = task(Vec::new());
+        done.status = TaskStatus::Done;
+        let mut aborted = task(Vec::new());
+        aborted.status = TaskStatus::Aborted;
+        let (active_id, done_id, aborted_id) = (active.id, done.id, aborted.id);
+        let tasks = [active, done, aborted];
+        assert!(initial_status(&tasks, &[]) == Ok(TaskStatus::Active));
+        assert!(initial_status(&tasks, &[done_id]) == Ok(TaskStatus::Active));
+        assert!(initial_status(&tasks, &[done_id, active_id]) == Ok(TaskStatus::Blocked));
+        assert!(initial_status(&tasks, &[active_id, aborted_id]) == Err(StatusCode::CONFLICT));
+        assert!(initial_status(&tasks, &[Uuid::new_v4()]) == Err(StatusCode::BAD_REQUEST));
+    }
+
+    #[tokio::test]
+    async fn dependent_is_unblocked_once_every_dependency_is_done() {
+        let mut store = Store::default();
+        let (first, second) = (task(Vec::new()), task(Vec::new()));
+        let (first_id, second_id) = (first.id, second.id);
+        store.insert(first);
+        store.insert(second);
+        let dependent = insert_blocked(&mut store, vec![first_id, second_id]);
+        finish(&mut store, first_id, TaskStatus::Done);
+        assert!(status(&store, &dependent) == TaskStatus::Blocked);
+        finish(&mut store, second_id, TaskStatus::Done);
+        assert!(status(&store, &dependent) == TaskStatus::Active);
+        assert!(store.get(&dependent).unwrap().started_at.is_some());
+    }
+
+    #[tokio::test]
+    async fn abort_cascades_to_the_dependents_transitively() {
+        let mut store = Store::default();
+        let root = task(Vec::new());
+        let root_id = root.id;
+        store.insert(root);
+        let child = insert_blocked(&mut store, vec![root_id]);
+        let grandchild = insert_blocked(&mut store, vec![child]);
+        let unrelated = insert_blocked(&mut store, vec![Uuid::new_v4()]);
+        finish(&mut store, root_id, TaskStatus::Aborted);
+        for uuid in [child, grandchild] {
+            let task = store.get(&uuid).unwrap();
+            assert!(task.status == TaskStatus::Aborted);
+            assert!(task.finished_at.is_some());
+        }
+        assert_eq!(
+            store.get(&grandchild).unwrap().description_result,
+            format!("dependency {} was aborted", child)
+        );
+        assert!(status(&store, &unrelated) == TaskStatus::Blocked);
+    }
+
+    #[tokio::test]
+    async fn forgotten_dependency_counts_as_done() {
+        let mut store = Store::default();
+        let (forgotten, last) = (task(Vec::new()), task(Vec::new()));
+        let (forgotten_id, last_id) = (forgotten.id, last.id);
+        store.insert(forgotten);
+        store.insert(last);
+        let dependent = insert_blocked(&mut store, vec![forgotten_id, last_id]);
+        finish(&mut store, forgotten_id, TaskStatus::Done);
+        store.remove(&forgotten_id);
+        finish(&mut store, last_id, TaskStatus::Done);
+        assert!(status(&store, &dependent) == TaskStatus::Active);
+    }
+}
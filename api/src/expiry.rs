@@ -0,0 +1,258 @@
+use std::{
+    cmp::Reverse,
+    collections::{BinaryHeap, HashMap},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use tasks_tracker_common::unix_timestamp;
+use tokio::{
+    sync::Notify,
+    time::{timeout_at, Instant},
+};
+use uuid::Uuid;
+
+use crate::store::Store;
+
+/// deadlines of the tasks, ordered by time of expiration.
+// a single reaper waits for the next deadline instead of one timer per task.
+// cancelled or rescheduled deadlines stay in the heap and are ignored when popped.
+// the tokio clock is used, so it can be paused and advanced in tests, and the wall clock converting the
+// timestamps of the tasks to deadlines can be replaced.
+pub(crate) struct Expiry {
+    heap: BinaryHeap<Reverse<(Instant, Uuid)>>,
+    // current deadline of each task, a deadline in the heap which is not here is stale.
+    deadlines: HashMap<Uuid, Instant>,
+    // wakes up the reaper when an earlier deadline is scheduled.
+    wake: Arc<Notify>,
+    // current unix timestamp in seconds.
+    clock: fn() -> u64,
+}
+
+impl Default for Expiry {
+    fn default() -> Self {
+        Expiry {
+            heap: BinaryHeap::new(),
+            deadlines: HashMap::new(),
+            wake: Arc::default(),
+            clock: unix_timestamp,
+        }
+    }
+}
+
+impl Expiry {
+    // set the deadline of a task given as a unix timestamp, replacing the previous one.
+    pub(crate) fn schedule_at(&mut self, uuid: Uuid, timestamp: u64) {
+        let seconds = timestamp.saturating_sub((self.clock)());
+        self.schedule(uuid, Instant::now() + Duration::from_secs(seconds));
+    }
+    // set the deadline of a task, replacing the previous one.
+    pub(crate) fn schedule(&mut self, uuid: Uuid, deadline: Instant) {
+        let earliest = self.next_deadline();
+        self.deadlines.insert(uuid, deadline);
+        self.heap.push(Reverse((deadline, uuid)));
+        if earliest.is_none_or(|earliest| deadline < earliest) {
+            self.wake.notify_one();
+        }
+    }
+    pub(crate) fn cancel(&mut self, uuid: &Uuid) {
+        self.deadlines.remove(uuid);
+    }
+    // remove and return the tasks whose deadline is passed.
+    pub(crate) fn pop_expired(&mut self, now: Instant) -> Vec<Uuid> {
+        let mut expired = Vec::new();
+        while let Some(Reverse((deadline, uuid))) = self.heap.peek().copied() {
+            if deadline > now {
+                break;
+            }
+            self.heap.pop();
+            if self.deadlines.get(&uuid) == Some(&deadline) {
+                self.deadlines.remove(&uuid);
+                expired.push(uuid);
+            }
+        }
+        expired
+    }
+    // earliest deadline still scheduled, stale deadlines are discarded.
+    pub(crate) fn next_deadline(&mut self) -> Option<Instant> {
+        while let Some(Reverse((deadline, uuid))) = self.heap.peek().copied() {
+            if self.deadlines.get(&uuid) == Some(&deadline) {
+                return Some(deadline);
+            }
+            self.heap.pop();
+        }
+        None
+    }
+}
+
+/// remove the finished tasks once their deadline is passed.
+// expired tasks are removed in batch under a single lock of the store, with a single pass over the tasks.
+pub(crate) async fn reaper(store: Arc<Mutex<Store>>) {
+    let wake = store.lock().unwrap().expiry.wake.clone();
+    loop {
+        let next_deadline = {
+            let mut store = store.lock().unwrap();
+            let expired = store.expiry.pop_expired(Instant::now());
+            store.expire(expired);
+            store.expiry.next_deadline()
+        };
+        match next_deadline {
+            Some(deadline) => {
+                let _ = timeout_at(deadline, wake.notified()).await;
+            }
+            None => wake.notified().await,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tasks_tracker_common::{NewTask, Task, TaskStatus};
+
+    use super::*;
+
+    // fixed wall clock, the paused tokio clock alone moves the deadlines.
+    const NOW: u64 = 1_700_000_000;
+
+    fn clock() -> u64 {
+        NOW
+    }
+
+    fn expiry() -> Expiry {
+        Expiry {
+            clock,
+            ..Expiry::default()
+        }
+    }
+
+    fn finished_task(duration: u32) -> Task {
+        let mut task = NewTask {
+            duration,
+            ..NewTask::default()
+        }
+        .to_task();
+        task.status = TaskStatus::Done;
+        task.finished_at = Some(NOW);
+        task
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn schedule_expires_at_the_deadline() {
+        let mut expiry = expiry();
+        let uuid = Uuid::new_v4();
+        expiry.schedule_at(uuid, NOW + 60);
+        assert_eq!(
+            expiry.next_deadline(),
+            Some(Instant::now() + Duration::from_secs(60))
+        );
+        tokio::time::advance(Duration::from_secs(59)).await;
+        assert!(expiry.pop_expired(Instant::now()).is_empty());
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(expiry.pop_expired(Instant::now()), vec![uuid]);
+        assert_eq!(expiry.next_deadline(), None);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn past_timestamp_expires_immediately() {
+        let mut expiry = expiry();
+        let uuid = Uuid::new_v4();
+        expiry.schedule_at(uuid, NOW - 10);
+        assert_eq!(expiry.pop_expired(Instant::now()), vec![uuid]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reschedule_replaces_the_deadline() {
+        let mut expiry = expiry();
+        let uuid = Uuid::new_v4();
+        expiry.schedule_at(uuid, NOW + 10);
+        expiry.schedule_at(uuid, NOW + 30);
+        tokio::time::advance(Duration::from_secs(10)).await;
+        assert!(expiry.pop_expired(Instant::now()).is_empty());
+        assert_eq!(
+            expiry.next_deadline(),
+            Some(Instant::now() + Duration::from_secs(20))
+        );
+        tokio::time::advance(Duration::from_secs(20)).await;
+        assert_eq!(expiry.pop_expired(Instant::now()), vec![uuid]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn cancel_discards_the_deadline() {
+        let mut expiry = expiry();
+        let cancelled = Uuid::new_v4();
+        let kept = Uuid::new_v4();
+        expiry.schedule_at(cancelled, NOW + 10);
+        expiry.schedule_at(kept, NOW + 20);
+        expiry.cancel(&cancelled);
+        assert_eq!(
+            expiry.next_deadline(),
+            Some(Instant::now() + Duration::from_secs(20))
+        );
+        tokio::time::advance(Duration::from_secs(20)).await;
+        assert_eq!(expiry.pop_expired(Instant::now()), vec![kept]);
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn pop_expired_returns_the_tasks_in_order_of_deadline() {
+        let mut expiry = expiry();
+        let first = Uuid::new_v4();
+        let second = Uuid::new_v4();
+        let third = Uuid::new_v4();
+        expiry.schedule_at(second, NOW + 20);
+        expiry.schedule_at(third, NOW + 30);
+        expiry.schedule_at(first, NOW + 10);
+        tokio::time::advance(Duration::from_secs(25)).await;
+        assert_eq!(expiry.pop_expired(Instant::now()), vec![first, second]);
+        assert_eq!(
+            expiry.next_deadline(),
+            Some(Instant::now() + Duration::from_secs(5))
+        );
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn reaper_removes_the_finished_task_after_its_duration() {
+        let mut store = Store::default();
+        store.expiry = expiry();
+        let store = Arc::new(Mutex::new(store));
+        tokio::spawn(reaper(store.clone()));
+        let kept = finished_task(120);
+        let forgotten = finished_task(60);
+        let (kept_id, forgotten_id) = (kept.id, forgotten.id);
+        {
+            let mut store = store.lock().unwrap();
+            store.insert(kept);
+            store.insert(forgotten);
+            store.schedule_forget(kept_id);
+            store.schedule_forget(forgotten_id);
+        }
+        tokio::time::sleep(Duration::from_secs(59)).await;
+        assert!(store.lock().unwrap().get(&forgotten_id).is_some());
+        tokio::time::sleep(Duration::from_secs(2)).await;
+        let store = store.lock().unwrap();
+        assert!(store.get(&forgotten_id).is_none());
+        assert!(store.get(&kept_id).is_some());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn expire_forgets_the_finished_tasks_and_aborts_the_unfinished() {
+        let mut store = Store::default();
+        store.expiry = expiry();
+        let first = finished_task(60);
+        let second = finished_task(60);
+        let kept = finished_task(60);
+        let mut unfinished = finished_task(60);
+        unfinished.status = TaskStatus::Active;
+        unfinished.finished_at = None;
+        let ids = [first.id, second.id, kept.id, unfinished.id];
+        for task in [first, second, kept, unfinished] {
+            store.insert(task);
+        }
+        store.expire(vec![ids[0], ids[1], ids[3]]);
+        assert!(store.get(&ids[0]).is_none());
+        assert!(store.get(&ids[1]).is_none());
+        assert!(store.get(&ids[2]).is_some());
+        let aborted = store.get(&ids[3]).unwrap();
+        assert!(aborted.status == TaskStatus::Aborted);
+        assert!(aborted.finished_at.is_some());
+    }
+}
@@ -0,0 +1,44 @@
+use axum::{extract::State, http::StatusCode, response::IntoResponse};
+
+use crate::{notify::pending_notifications, AppState};
+
+/// liveness of the process, always ok if the server responds.
+pub(crate) async fn healthz() -> impl IntoResponse {
+    "ok"
+}
+
+/// readiness of the server to handle requests.
+// the store must not be poisoned by a panic and the push notifications in flight must stay under the configured backlog.
+pub(crate) async fn readyz(State(state): State<AppState>) -> impl IntoResponse {
+    let store_ok = !state.store.is_poisoned();
+    let backlog = pending_notifications();
+    let notifier_ok = backlog <= state.max_notification_backlog;
+    let status = if store_ok && notifier_ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (
+        status,
+        format!(
+            "store: {}\nnotifications in flight: {}/{}\n",
+            if store_ok { "ok" } else { "poisoned" },
+            backlog,
+            state.max_notification_backlog
+        ),
+    )
+}
+
+/// name and version of the server.
+pub(crate) async fn version() -> impl IntoResponse {
+    format!(
+        "{} {}\nprofile: {}\n",
+        env!("CARGO_PKG_NAME"),
+        env!("CARGO_PKG_VERSION"),
+        if cfg!(debug_assertions) {
+            "debug"
+        } else {
+            "release"
+        }
+    )
+}
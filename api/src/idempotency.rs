@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use tasks_tracker_common::unix_timestamp;
+use uuid::Uuid;
+
+/// keys given by clients with the header Idempotency-Key when creating a task.
+// a repeated key with the same credential returns the task already created instead of creating a new one.
+// keys are forgotten after the retention window.
+pub(crate) struct IdempotencyKeys {
+    // (credential, key) => (id of the task created, timestamp of creation)
+    keys: HashMap<(String, String), (Uuid, u64)>,
+    // retention window in seconds.
+    window: u64,
+    // current unix timestamp in seconds.
+    clock: fn() -> u64,
+}
+
+impl IdempotencyKeys {
+    pub(crate) fn new(window: u64) -> Self {
+        IdempotencyKeys {
+            keys: HashMap::new(),
+            window,
+            clock: unix_timestamp,
+        }
+    }
+    // returns the task created with this key and credential if the key is still retained.
+    pub(crate) fn get(&mut self, credential: &str, key: &str) -> Option<Uuid> {
+        let now = (self.clock)();
+        let window = self.window;
+        self.keys
+            .retain(|_, (_, created)| created.saturating_add(window) > now);
+        self.keys
+            .get(&(credential.to_string(), key.to_string()))
+            .map(|(uuid, _)| *uuid)
+    }
+    pub(crate) fn insert(&mut self, credential: &str, key: &str, uuid: Uuid) {
+        self.keys.insert(
+            (credential.to_string(), key.to_string()),
+            (uuid, (self.clock)()),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: u64 = 1_700_000_000;
+
+    fn clock() -> u64 {
+        NOW
+    }
+
+    fn keys() -> IdempotencyKeys {
+        IdempotencyKeys {
+            clock,
+            ..IdempotencyKeys::new(60)
+        }
+    }
+
+    #[test]
+    fn repeated_key_returns_the_same_task() {
+        let mut keys = keys();
+        let uuid = Uuid::new_v4();
+        assert_eq!(keys.get("Bearer create", "key"), None);
+        keys.insert("Bearer create", "key", uuid);
+        assert_eq!(keys.get("Bearer create", "key"), Some(uuid));
+        assert_eq!(keys.get("Bearer create", "key"), Some(uuid));
+        assert_eq!(keys.get("Bearer create", "other"), None);
+    }
+
+    #[test]
+    fn key_is_scoped_to_its_credential() {
+        let mut keys = keys();
+        let (first, second) = (Uuid::new_v4(), Uuid::new_v4());
+        keys.insert("Bearer create", "key", first);
+        assert_eq!(keys.get("Bearer admin", "key"), None);
+        keys.insert("Bearer admin", "key", second);
+        assert_eq!(keys.get("Bearer create", "key"), Some(first));
+        assert_eq!(keys.get("Bearer admin", "key"), Some(second));
+    }
+
+    #[test]
+    fn key_expires_after_the_window() {
+        fn before_end() -> u64 {
+            NOW + 59
+        }
+        fn end() -> u64 {
+            NOW + 60
+        }
+        let mut keys = keys();
+        let uuid = Uuid::new_v4();
+        keys.insert("Bearer create", "key", uuid);
+        keys.clock = before_end;
+        assert_eq!(keys.get("Bearer create", "key"), Some(uuid));
+        keys.clock = end;
+        assert_eq!(keys.get("Bearer create", "key"), None);
+        // the expired key is forgotten, not only hidden.
+        keys.clock = clock;
+        assert_eq!(keys.get("Bearer create", "key"), None);
+    }
+}
@@ -0,0 +1,186 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    http::{
+        header::{ALLOW, ETAG},
+        HeaderMap, StatusCode,
+    },
+    response::{AppendHeaders, IntoResponse},
+};
+use bincode::config::Configuration;
+use tasks_tracker_common::{unix_timestamp, TaskStatus};
+use uuid::Uuid;
+
+use crate::{authorize::is_authorized, if_match, AppState, ClientPrivilege};
+
+// a requirement of a label selector.
+enum Requirement {
+    Equal(String, String),
+    NotEqual(String, String),
+    Exists(String),
+    NotExists(String),
+}
+
+/// label selector used to filter tasks, for example `env=prod,team!=billing,critical,!draft`.
+pub(crate) struct Selector(Vec<Requirement>);
+
+impl Selector {
+    // returns None if the selector is malformed, a key is empty or starts with `!` like in `!env=prod`.
+    pub(crate) fn parse(selector: &str) -> Option<Selector> {
+        let mut requirements = Vec::new();
+        for requirement in selector.split(',').map(str::trim).filter(|r| !r.is_empty()) {
+            let requirement = if let Some((key, value)) = requirement.split_once("!=") {
+                Requirement::NotEqual(key.trim().to_string(), value.trim().to_string())
+            } else if let Some((key, value)) = requirement
+                .split_once("==")
+                .or_else(|| requirement.split_once('='))
+            {
+                Requirement::Equal(key.trim().to_string(), value.trim().to_string())
+            } else if let Some(key) = requirement.strip_prefix('!') {
+                Requirement::NotExists(key.trim().to_string())
+            } else {
+                Requirement::Exists(requirement.to_string())
+            };
+            match &requirement {
+                Requirement::Equal(key, _)
+                | Requirement::NotEqual(key, _)
+                | Requirement::Exists(key)
+                | Requirement::NotExists(key)
+                    if key.is_empty() || key.starts_with('!') =>
+                {
+                    return None
+                }
+                _ => requirements.push(requirement),
+            }
+        }
+        Some(Selector(requirements))
+    }
+    pub(crate) fn matches(&self, labels: &BTreeMap<String, String>) -> bool {
+        self.0.iter().all(|requirement| match requirement {
+            Requirement::Equal(key, value) => labels.get(key) == Some(value),
+            Requirement::NotEqual(key, value) => labels.get(key) != Some(value),
+            Requirement::Exists(key) => labels.contains_key(key),
+            Requirement::NotExists(key) => !labels.contains_key(key),
+        })
+    }
+}
+
+/// replace the labels and tags of a task, with the update token.
+pub(crate) async fn set_labels(
+    headers: HeaderMap,
+    Path(uuid): Path<Uuid>,
+    State(state): State<AppState>,
+    body: Bytes,
+) -> impl IntoResponse {
+    let authorized_status = is_authorized(&headers, &state, ClientPrivilege::Update(uuid));
+    if authorized_status != StatusCode::OK {
+        return authorized_status.into_response();
+    }
+    let Ok(((labels, tags), _)) = bincode::decode_from_slice::<
+        (BTreeMap<String, String>, BTreeSet<String>),
+        Configuration,
+    >(&body, state.config_bincode) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let mut tasks = state.store.lock().unwrap();
+    let Some(task) = tasks.get_mut(&uuid) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if !if_match(&headers, task) {
+        return StatusCode::PRECONDITION_FAILED.into_response();
+    }
+    if task.status == TaskStatus::Done || task.status == TaskStatus::Aborted {
+        return (
+            StatusCode::METHOD_NOT_ALLOWED,
+            AppendHeaders([(ALLOW, "GET")]),
+        )
+            .into_response();
+    }
+    let old_value = format!("{:?} {:?}", task.labels, task.tags);
+    let new_value = format!("{:?} {:?}", labels, tags);
+    task.labels = labels;
+    task.tags = tags;
+    task.touch(unix_timestamp());
+    let etag = task.etag();
+    tasks.audit.record(uuid, "labels", old_value, new_value);
+    (StatusCode::ACCEPTED, [(ETAG, etag)]).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(labels: &[(&str, &str)]) -> BTreeMap<String, String> {
+        labels
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect()
+    }
+
+    // requirements of a selector as (operator, key, value).
+    fn requirements(selector: &Selector) -> Vec<(&str, &str, &str)> {
+        selector
+            .0
+            .iter()
+            .map(|requirement| match requirement {
+                Requirement::Equal(key, value) => ("=", key.as_str(), value.as_str()),
+                Requirement::NotEqual(key, value) => ("!=", key.as_str(), value.as_str()),
+                Requirement::Exists(key) => ("", key.as_str(), ""),
+                Requirement::NotExists(key) => ("!", key.as_str(), ""),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn parse_the_example_of_the_selectors() {
+        let selector = Selector::parse("env=prod,team!=billing").unwrap();
+        assert_eq!(
+            requirements(&selector),
+            [("=", "env", "prod"), ("!=", "team", "billing")]
+        );
+        assert!(selector.matches(&labels(&[("env", "prod"), ("team", "search")])));
+        assert!(selector.matches(&labels(&[("env", "prod")])));
+        assert!(!selector.matches(&labels(&[("env", "prod"), ("team", "billing")])));
+        assert!(!selector.matches(&labels(&[("env", "dev")])));
+    }
+
+    #[test]
+    fn parse_every_requirement() {
+        let selector = Selector::parse(" env == prod , critical, !draft,").unwrap();
+        assert_eq!(
+            requirements(&selector),
+            [
+                ("=", "env", "prod"),
+                ("", "critical", ""),
+                ("!", "draft", "")
+            ]
+        );
+        assert!(selector.matches(&labels(&[("env", "prod"), ("critical", "")])));
+        assert!(!selector.matches(&labels(&[("env", "prod")])));
+        assert!(!selector.matches(&labels(&[("env", "prod"), ("critical", ""), ("draft", "")])));
+    }
+
+    #[test]
+    fn empty_selector_matches_every_task() {
+        let selector = Selector::parse("").unwrap();
+        assert!(selector.0.is_empty());
+        assert!(selector.matches(&labels(&[])));
+    }
+
+    #[test]
+    fn parse_rejects_malformed_selectors() {
+        for selector in [
+            "=prod",
+            "==prod",
+            "!=billing",
+            "!",
+            "env=prod, =dev",
+            "!env=prod",
+            "!!draft",
+        ] {
+            assert!(Selector::parse(selector).is_none(), "{}", selector);
+        }
+    }
+}
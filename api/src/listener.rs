@@ -0,0 +1,159 @@
+use std::{
+    fs::Permissions,
+    future::IntoFuture,
+    net::SocketAddr,
+    os::unix::fs::{FileTypeExt, PermissionsExt},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use axum::Router;
+use axum_server::{tls_rustls::RustlsConfig, Handle};
+use hyper_util::{
+    rt::{TokioExecutor, TokioIo},
+    server::{conn::auto::Builder, graceful::GracefulShutdown},
+    service::TowerToHyperService,
+};
+use tokio::{
+    net::UnixListener,
+    select, spawn,
+    time::{sleep, Instant},
+};
+use tracing::{debug, info, warn};
+
+use crate::shutdown::Shutdown;
+
+// interval at which the tls certificate and key are checked for changes.
+const TLS_RELOAD_INTERVAL: Duration = Duration::from_secs(10);
+
+/// where the api accepts connections.
+pub(crate) enum Listener {
+    Tcp(SocketAddr),
+    // https, the certificate chain and the private key are pem files reloaded when they change.
+    Tls {
+        addr: SocketAddr,
+        cert: PathBuf,
+        key: PathBuf,
+    },
+    // unix domain socket created with the given permissions.
+    Unix {
+        path: PathBuf,
+        mode: u32,
+    },
+}
+
+// parse permissions given in octal, like 660.
+pub(crate) fn parse_mode(mode: &str) -> Result<u32, String> {
+    u32::from_str_radix(mode, 8).map_err(|e| format!("invalid octal permissions: {}", e))
+}
+
+/// serve the routes on the listener until the shutdown.
+// once the shutdown is requested, no connection is accepted and the requests in flight are awaited until the deadline.
+pub(crate) async fn serve(listener: Listener, routes: Router, shutdown: Shutdown) {
+    match listener {
+        Listener::Tcp(addr) => {
+            let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+            info!("listening on {}", addr);
+            let requested = shutdown.clone();
+            let server = axum::serve(
+                listener,
+                routes.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .with_graceful_shutdown(async move {
+                requested.requested().await;
+            });
+            select! {
+                result = server.into_future() => result.unwrap(),
+                _ = shutdown.deadline() => warn!("requests in flight are dropped"),
+            }
+        }
+        Listener::Tls { addr, cert, key } => {
+            // only one provider is compiled in, so it can not be already installed.
+            let _ = rustls::crypto::ring::default_provider().install_default();
+            let config = RustlsConfig::from_pem_file(&cert, &key)
+                .await
+                .expect("tls certificate and key must be readable");
+            spawn(reload_on_change(config.clone(), cert, key));
+            let handle = Handle::new();
+            let stopping = handle.clone();
+            spawn(async move {
+                let deadline = shutdown.requested().await;
+                stopping
+                    .graceful_shutdown(Some(deadline.saturating_duration_since(Instant::now())));
+            });
+            info!("listening on {} with tls", addr);
+            axum_server::bind_rustls(addr, config)
+                .handle(handle)
+                .serve(routes.into_make_service_with_connect_info::<SocketAddr>())
+                .await
+                .unwrap();
+        }
+        Listener::Unix { path, mode } => serve_unix(&path, mode, routes, shutdown).await,
+    }
+}
+
+async fn serve_unix(path: &Path, mode: u32, routes: Router, shutdown: Shutdown) {
+    // a socket left by a previous run would make the bind fail, other files are never removed.
+    if std::fs::symlink_metadata(path).is_ok_and(|m| m.file_type().is_socket()) {
+        std::fs::remove_file(path).unwrap();
+    }
+    let listener = UnixListener::bind(path).unwrap();
+    std::fs::set_permissions(path, Permissions::from_mode(mode))
+        .expect("permissions of the unix socket must be settable");
+    info!("listening on {}", path.display());
+    let builder = Builder::new(TokioExecutor::new());
+    let graceful = GracefulShutdown::new();
+    loop {
+        let stream = select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => stream,
+                Err(e) => {
+                    warn!("could not accept a connection: {}", e);
+                    continue;
+                }
+            },
+            _ = shutdown.requested() => break,
+        };
+        let service = TowerToHyperService::new(routes.clone());
+        let connection = graceful.watch(
+            builder
+                .serve_connection_with_upgrades(TokioIo::new(stream), service)
+                .into_owned(),
+        );
+        spawn(async move {
+            if let Err(e) = connection.await {
+                debug!("connection closed with an error: {}", e);
+            }
+        });
+    }
+    drop(listener);
+    let _ = std::fs::remove_file(path);
+    select! {
+        _ = graceful.shutdown() => {},
+        _ = shutdown.deadline() => warn!("requests in flight are dropped"),
+    }
+}
+
+// reload the certificate when the files are modified, so a renewal does not need a restart.
+// a failed reload, for example when only one of the files is written yet, is retried at the next check.
+async fn reload_on_change(config: RustlsConfig, cert: PathBuf, key: PathBuf) {
+    let mut loaded = (modified(&cert), modified(&key));
+    loop {
+        sleep(TLS_RELOAD_INTERVAL).await;
+        let current = (modified(&cert), modified(&key));
+        if current == loaded {
+            continue;
+        }
+        match config.reload_from_pem_file(&cert, &key).await {
+            Ok(()) => {
+                info!("tls certificate reloaded");
+                loaded = current;
+            }
+            Err(e) => warn!("could not reload the tls certificate: {}", e),
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path).and_then(|m| m.modified()).ok()
+}
@@ -0,0 +1,136 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, Query, State},
+    http::{
+        header::{ALLOW, ETAG},
+        HeaderMap, StatusCode,
+    },
+    response::{AppendHeaders, IntoResponse},
+};
+use bincode::config::Configuration;
+use serde::Deserialize;
+use tasks_tracker_common::{unix_timestamp, LogEntry, LogLevel, TaskStatus, LOG_LEN};
+use uuid::Uuid;
+
+use crate::{
+    authorize::is_authorized, if_match, notify::push_log_entry, AppState, ClientPrivilege,
+};
+
+// pagination of the log, entries with a sequence number greater or equal to `from` are returned.
+#[derive(Deserialize)]
+pub(crate) struct LogPage {
+    #[serde(default)]
+    from: u64,
+    limit: Option<usize>,
+}
+
+/// append an entry to the log of a task, with the update token.
+pub(crate) async fn append_log(
+    headers: HeaderMap,
+    Path(uuid): Path<Uuid>,
+    State(state): State<AppState>,
+    body: Bytes,
+) -> impl IntoResponse {
+    let authorized_status = is_authorized(&headers, &state, ClientPrivilege::Update(uuid));
+    if authorized_status != StatusCode::OK {
+        return authorized_status.into_response();
+    }
+    let Ok(((level, message), _)) = bincode::decode_from_slice::<(LogLevel, String), Configuration>(
+        &body,
+        state.config_bincode,
+    ) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    let mut tasks = state.store.lock().unwrap();
+    let Some(task) = tasks.get_mut(&uuid) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    // appending changes the revision of the task like any other change.
+    if !if_match(&headers, task) {
+        return StatusCode::PRECONDITION_FAILED.into_response();
+    }
+    // the log of a finished task is closed.
+    if task.status == TaskStatus::Done || task.status == TaskStatus::Aborted {
+        return (
+            StatusCode::METHOD_NOT_ALLOWED,
+            AppendHeaders([(ALLOW, "GET")]),
+        )
+            .into_response();
+    }
+    task.append_log(level, message, unix_timestamp());
+    // listeners follow the log live through the push notifications.
+    if let Some(entry) = task.log.last() {
+        push_log_entry(task, entry);
+    }
+    (StatusCode::ACCEPTED, [(ETAG, task.etag())]).into_response()
+}
+
+/// read a page of the log of a task, with the view token.
+pub(crate) async fn read_log(
+    headers: HeaderMap,
+    Path(uuid): Path<Uuid>,
+    Query(page): Query<LogPage>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let authorized_status = is_authorized(&headers, &state, ClientPrivilege::View(uuid));
+    if authorized_status != StatusCode::OK {
+        return authorized_status.into_response();
+    }
+    let tasks = state.store.lock().unwrap();
+    let Some(task) = tasks.get(&uuid) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    let entries = task
+        .log
+        .iter()
+        .filter(|e| e.sequence >= page.from)
+        .take(page.limit.unwrap_or(LOG_LEN))
+        .cloned()
+        .collect::<Vec<LogEntry>>();
+    bincode::encode_to_vec(entries, state.config_bincode)
+        .unwrap()
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::{
+        header::{AUTHORIZATION, IF_MATCH},
+        HeaderValue,
+    };
+    use tasks_tracker_common::{NewTask, BINCODE_CONFIG};
+
+    use super::*;
+    use crate::tests::state;
+
+    #[tokio::test]
+    async fn append_log_honours_if_match_and_returns_the_new_etag() {
+        let state = state();
+        let task = NewTask::default().to_task();
+        let (uuid, etag) = (task.id, task.etag());
+        let token = HeaderValue::from_str(&["Bearer ", &task.tokens.2].concat()).unwrap();
+        state.store.lock().unwrap().insert(task);
+        let server = axum_test::TestServer::new(crate::routes(state.clone())).unwrap();
+        let body = bincode::encode_to_vec((LogLevel::Info, "started".to_string()), BINCODE_CONFIG)
+            .unwrap();
+        let append = |etag: &str| {
+            server
+                .post(&format!("/tasks/{}/log", uuid))
+                .add_header(AUTHORIZATION, token.clone())
+                .add_header(IF_MATCH, HeaderValue::from_str(etag).unwrap())
+                .bytes(body.clone().into())
+        };
+        let response = append(&etag).await;
+        response.assert_status(StatusCode::ACCEPTED);
+        let new_etag = response.header(ETAG);
+        assert_ne!(new_etag, etag.as_str());
+        // the etag held before the append is now stale.
+        append(&etag)
+            .await
+            .assert_status(StatusCode::PRECONDITION_FAILED);
+        append(new_etag.to_str().unwrap())
+            .await
+            .assert_status(StatusCode::ACCEPTED);
+        assert_eq!(state.store.lock().unwrap().get(&uuid).unwrap().log.len(), 2);
+    }
+}
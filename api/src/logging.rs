@@ -0,0 +1,78 @@
+use std::{fs::File, path::Path, sync::Mutex, time::Instant};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use clap::ValueEnum;
+use serde::Deserialize;
+use tracing::{info, info_span, Instrument, Level};
+use tracing_subscriber::{
+    filter::Targets, fmt, layer::SubscriberExt, util::SubscriberInitExt, EnvFilter, Layer,
+};
+
+// format of the logs written on stderr.
+#[derive(Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LogFormat {
+    Text,
+    Json,
+}
+
+/// install the subscriber writing the logs.
+// level is a filter directive like `info` or `task_tracker_rs_api=debug`.
+// if an audit file is given, the privileged actions (target "audit") are also written to it as json, with the request they come from.
+pub(crate) fn init(level: &str, format: LogFormat, audit_file: Option<&Path>) {
+    let filter = EnvFilter::try_new(level).expect("level is checked when loading the arguments");
+    let logs = match format {
+        LogFormat::Text => fmt::layer().with_writer(std::io::stderr).boxed(),
+        LogFormat::Json => fmt::layer().json().with_writer(std::io::stderr).boxed(),
+    }
+    .with_filter(filter);
+    let audit = audit_file.map(|path| {
+        let file = File::options()
+            .create(true)
+            .append(true)
+            .open(path)
+            .expect("audit log file must be writable");
+        fmt::layer()
+            .json()
+            .with_writer(Mutex::new(file))
+            .with_filter(
+                Targets::new()
+                    .with_target("audit", Level::INFO)
+                    .with_target("request", Level::INFO),
+            )
+    });
+    tracing_subscriber::registry().with(logs).with(audit).init();
+}
+
+/// middleware logging every request with its route, status and latency.
+// only the path is logged, never the query or the headers which can contain tokens.
+// the privilege used is recorded on the span by the authorization.
+pub(crate) async fn trace_request(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", MatchedPath::as_str)
+        .to_string();
+    let span = info_span!(
+        target: "request",
+        "request",
+        method = %request.method(),
+        route,
+        path = request.uri().path(),
+        privilege = tracing::field::Empty,
+    );
+    let start = Instant::now();
+    let response = next.run(request).instrument(span.clone()).await;
+    span.in_scope(|| {
+        info!(
+            target: "access",
+            status = response.status().as_u16(),
+            latency_ms = start.elapsed().as_secs_f64() * 1000.0,
+        )
+    });
+    response
+}
@@ -1,33 +1,153 @@
 use bincode::config::Configuration;
 use std::{
+    net::{Ipv4Addr, SocketAddr},
+    path::PathBuf,
     sync::{Arc, Mutex},
     time::Duration,
 };
+use tasks_tracker_common::unix_timestamp;
 use tasks_tracker_common::NewTask;
 use tasks_tracker_common::Task;
 use tasks_tracker_common::TaskStatus;
+use tasks_tracker_common::{ProgressCount, TaskUpdate};
 
-use authorize::is_authorized;
+use authorize::{has_privilege, is_authorized, task_with_uuid};
 use axum::{
     body::Bytes,
-    extract::{Path, State},
-    http::{header::ALLOW, HeaderMap, Method, StatusCode},
-    response::{AppendHeaders, IntoResponse},
-    routing::{delete, get, patch, post},
-    Router,
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::{
+        header::{ACCEPT, ALLOW, AUTHORIZATION, ETAG, IF_MATCH},
+        HeaderMap, Method, StatusCode,
+    },
+    middleware,
+    response::{AppendHeaders, IntoResponse, Response},
+    routing::{delete, get, patch, post, put},
+    Json, Router,
 };
 use clap::Parser;
-use tokio::{spawn, time::sleep};
+use idempotency::IdempotencyKeys;
+use labels::Selector;
+use listener::Listener;
+use logging::LogFormat;
+use metrics::METRICS;
+use notify::push_notification;
+use serde::Deserialize;
+use shutdown::Shutdown;
+use store::Store;
+use tokio::spawn;
+use tracing::info;
 use uuid::Uuid;
 
+mod admin;
+mod audit;
 mod authorize;
+mod batch;
+mod config;
+mod dependencies;
+mod expiry;
+mod health;
+mod idempotency;
+mod labels;
+mod listener;
+mod log;
+mod logging;
+mod metrics;
+mod notify;
+mod shutdown;
+mod stats;
+mod store;
+mod ui;
 
+// every setting can also be given by an environment variable TASKS_TRACKER_<NAME>, or in the config file with the name of its flag.
+// the command line takes precedence over the environment, which takes precedence over the config file.
 #[derive(Parser)]
 struct Args {
-    token_create: String,
+    /// toml file with the settings, for example `port = 8000` or `token-create-file = "/run/secrets/create"`.
+    #[arg(long, env = "TASKS_TRACKER_CONFIG")]
+    config: Option<PathBuf>,
+    /// filter directive of the logs, for example info or task_tracker_rs_api=debug.
+    #[arg(long, env = "TASKS_TRACKER_LOG_LEVEL", default_value = "info")]
+    log_level: String,
+    /// format of the logs.
+    #[arg(long, env = "TASKS_TRACKER_LOG_FORMAT", value_enum, default_value_t = LogFormat::Text)]
+    log_format: LogFormat,
+    /// file where the privileged actions are logged as json, in addition to the logs.
+    #[arg(long, env = "TASKS_TRACKER_AUDIT_LOG")]
+    audit_log: Option<PathBuf>,
+    /// token required to create a task.
+    // tokens given on the command line are visible to other users in the list of processes, prefer the environment or a file.
+    #[arg(env = "TASKS_TRACKER_TOKEN_CREATE", hide_env_values = true)]
+    token_create: Option<String>,
+    /// token giving every privilege, listing the tasks and the admin endpoints are refused without it.
+    #[arg(env = "TASKS_TRACKER_TOKEN_ADMIN", hide_env_values = true)]
     token_admin: Option<String>,
-    #[arg(default_value_t = 8000)]
+    /// port to listen on, on 127.0.0.1.
+    #[arg(env = "TASKS_TRACKER_PORT", default_value_t = 8000)]
     port: u16,
+    /// file containing the creation token, used instead of the token itself.
+    #[arg(
+        long,
+        env = "TASKS_TRACKER_TOKEN_CREATE_FILE",
+        conflicts_with = "token_create"
+    )]
+    token_create_file: Option<PathBuf>,
+    /// file containing the admin token, used instead of the token itself.
+    #[arg(
+        long,
+        env = "TASKS_TRACKER_TOKEN_ADMIN_FILE",
+        conflicts_with = "token_admin"
+    )]
+    token_admin_file: Option<PathBuf>,
+    /// address and port to listen on, ipv4 or ipv6, instead of 127.0.0.1 and the port.
+    #[arg(long, env = "TASKS_TRACKER_BIND")]
+    bind: Option<SocketAddr>,
+    /// listen on a unix domain socket instead of tcp.
+    #[arg(long, env = "TASKS_TRACKER_UNIX_SOCKET")]
+    unix_socket: Option<PathBuf>,
+    /// permissions of the unix socket, in octal.
+    #[arg(long, env = "TASKS_TRACKER_UNIX_SOCKET_MODE", default_value = "660", value_parser = listener::parse_mode)]
+    unix_socket_mode: u32,
+    /// certificate chain in pem, to serve the api over https with the private key.
+    #[arg(long, env = "TASKS_TRACKER_TLS_CERT")]
+    tls_cert: Option<PathBuf>,
+    /// private key in pem of the certificate.
+    #[arg(long, env = "TASKS_TRACKER_TLS_KEY")]
+    tls_key: Option<PathBuf>,
+    /// seconds to wait for the requests and push notifications in flight when stopping.
+    #[arg(long, env = "TASKS_TRACKER_SHUTDOWN_TIMEOUT", default_value_t = 30)]
+    shutdown_timeout: u64,
+    /// reject updates making the progress of a task go backwards, unless they are forced.
+    #[arg(long, env = "TASKS_TRACKER_MONOTONIC_PROGRESS")]
+    monotonic_progress: bool,
+    /// serve the metrics on a separate port instead of the port of the api.
+    #[arg(long, env = "TASKS_TRACKER_METRICS_PORT")]
+    metrics_port: Option<u16>,
+    /// number of push notifications in flight above which the server is not ready.
+    #[arg(
+        long,
+        env = "TASKS_TRACKER_MAX_NOTIFICATION_BACKLOG",
+        default_value_t = 1000
+    )]
+    max_notification_backlog: usize,
+    /// seconds after which a push notification without response is considered failed.
+    #[arg(long, env = "TASKS_TRACKER_NOTIFICATION_TIMEOUT", default_value_t = 10)]
+    notification_timeout: u64,
+    /// maximum seconds a task can stay unfinished before being aborted by the server.
+    #[arg(long, env = "TASKS_TRACKER_MAX_LIFETIME")]
+    max_lifetime: Option<u32>,
+    /// maximum seconds a finished task is retained, longer durations are reduced to it.
+    #[arg(long, env = "TASKS_TRACKER_MAX_DURATION")]
+    max_duration: Option<u32>,
+    /// maximum size in bytes of the body of a request, which contains the payload of a task.
+    #[arg(long, env = "TASKS_TRACKER_MAX_BODY_SIZE", default_value_t = 2 * 1024 * 1024)]
+    max_body_size: usize,
+    /// seconds during which an Idempotency-Key given at creation is retained.
+    #[arg(
+        long,
+        env = "TASKS_TRACKER_IDEMPOTENCY_WINDOW",
+        default_value_t = 86400
+    )]
+    idempotency_window: u64,
 }
 
 // Possible type of authorization
@@ -39,52 +159,172 @@ enum ClientPrivilege {
     Abort(Uuid),
     Update(Uuid),
     List,
+    Admin,
+}
+
+impl ClientPrivilege {
+    fn name(&self) -> &'static str {
+        match self {
+            ClientPrivilege::Creation => "creation",
+            ClientPrivilege::View(_) => "view",
+            ClientPrivilege::Abort(_) => "abort",
+            ClientPrivilege::Update(_) => "update",
+            ClientPrivilege::List => "list",
+            ClientPrivilege::Admin => "admin",
+        }
+    }
 }
 
 #[derive(Clone)]
 struct AppState {
-    tasks: Arc<Mutex<Vec<Task>>>,
+    store: Arc<Mutex<Store>>,
     token_admin: Option<String>,
     token_create: String,
     config_bincode: bincode::config::Configuration,
+    idempotency_keys: Arc<Mutex<IdempotencyKeys>>,
+    monotonic_progress: bool,
+    max_lifetime: Option<u32>,
+    max_duration: Option<u32>,
+    max_notification_backlog: usize,
 }
 
 fn routes(state: AppState) -> Router {
     Router::new()
+        .route("/healthz", get(health::healthz))
+        .route("/readyz", get(health::readyz))
+        .route("/version", get(health::version))
         .route("/tasks", get(list_tasks))
         .route("/tasks", post(create_task))
+        // a static segment takes priority over the id of a task.
+        .route("/tasks/batch", post(batch::batch))
+        .route("/admin/abort", post(admin::abort_tasks))
+        .route("/stats", get(stats::stats))
         .route("/tasks/:id", get(view_task))
+        .route("/tasks/by-ref/:scope/:ref", get(view_task_by_ref))
         .route("/tasks/:id", patch(update_task))
         .route("/tasks/:id", delete(update_task))
+        .route("/tasks/:id/purge", post(purge_task))
+        .route("/tasks/:id/retention", put(set_retention))
+        .route("/tasks/:id/log", get(log::read_log))
+        .route("/tasks/:id/log", post(log::append_log))
+        .route("/tasks/:id/labels", put(labels::set_labels))
+        .route("/tasks/:id/audit", get(audit::read_audit))
+        .route("/ui", get(ui::dashboard))
+        .route("/ui/tasks/:id", get(ui::task_page))
+        .layer(middleware::from_fn(metrics::track_latency))
+        .layer(middleware::from_fn(logging::trace_request))
+        .layer(middleware::from_fn(audit::track_actor))
+        .with_state(state)
+}
+
+// prometheus metrics, protected by the admin token.
+fn metrics_routes(state: AppState) -> Router {
+    Router::new()
+        .route("/metrics", get(metrics::metrics))
         .with_state(state)
 }
 
 #[tokio::main]
 async fn main() {
-    // get envs for admin token and port number to listen to
-    let args = Args::parse();
+    // get the settings from the arguments, the environment and the config file
+    let args = config::load_args();
+    logging::init(&args.log_level, args.log_format, args.audit_log.as_deref());
 
     // create the app struct
     let state = AppState {
-        tasks: Arc::new(Mutex::new(Vec::new())),
+        store: Arc::new(Mutex::new(Store::default())),
         token_admin: args.token_admin,
-        token_create: args.token_create,
+        token_create: args
+            .token_create
+            .expect("presence is checked when loading the arguments"),
         config_bincode: bincode::config::standard(),
+        idempotency_keys: Arc::new(Mutex::new(IdempotencyKeys::new(args.idempotency_window))),
+        monotonic_progress: args.monotonic_progress,
+        max_lifetime: args.max_lifetime,
+        max_duration: args.max_duration,
+        max_notification_backlog: args.max_notification_backlog,
     };
 
+    notify::init(Duration::from_secs(args.notification_timeout));
+    // a single reaper forgets the finished tasks after their duration.
+    spawn(expiry::reaper(state.store.clone()));
+
+    let addr = args
+        .bind
+        .unwrap_or(SocketAddr::from((Ipv4Addr::LOCALHOST, args.port)));
+    let listener = match (args.unix_socket, args.tls_cert, args.tls_key) {
+        (Some(path), None, None) => Listener::Unix {
+            path,
+            mode: args.unix_socket_mode,
+        },
+        (None, Some(cert), Some(key)) => Listener::Tls { addr, cert, key },
+        (None, None, None) => Listener::Tcp(addr),
+        _ => unreachable!("the listener is checked when loading the arguments"),
+    };
     // create routes
-    let routes = routes(state);
-    let adr = format!("127.0.0.1:{}", args.port);
-    let listener = tokio::net::TcpListener::bind(&adr).await.unwrap();
-    axum::serve(listener, routes).await.unwrap();
+    // the metrics are served on their own port if one is given, on the same address as the api.
+    let routes = if let Some(metrics_port) = args.metrics_port {
+        let listener = tokio::net::TcpListener::bind((addr.ip(), metrics_port))
+            .await
+            .unwrap();
+        let metrics_routes = metrics_routes(state.clone());
+        spawn(async move { axum::serve(listener, metrics_routes).await.unwrap() });
+        routes(state)
+    } else {
+        routes(state.clone()).merge(metrics_routes(state))
+    }
+    .layer(DefaultBodyLimit::max(args.max_body_size));
+    // on SIGINT or SIGTERM, the server stops accepting connections, then waits for the requests and notifications in flight.
+    // tasks are only in memory, they are lost when the server stops.
+    let shutdown = Shutdown::on_signal(Duration::from_secs(args.shutdown_timeout));
+    listener::serve(listener, routes, shutdown.clone()).await;
+    shutdown::drain_notifications(shutdown.requested().await).await;
+    info!("stopped");
 }
 
-async fn list_tasks(headers: HeaderMap, State(state): State<AppState>) -> impl IntoResponse {
+// filters of the list of tasks.
+// labels is a label selector, tags a comma separated list of tags that the tasks must all have.
+#[derive(Deserialize)]
+struct ListFilter {
+    labels: Option<String>,
+    tags: Option<String>,
+}
+
+async fn list_tasks(
+    headers: HeaderMap,
+    Query(filter): Query<ListFilter>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
     let authorized_status = is_authorized(&headers, &state, ClientPrivilege::List);
     if authorized_status != StatusCode::OK {
         return authorized_status.into_response();
     }
-    let tasks = state.tasks.lock().unwrap().clone();
+    let Some(selector) = Selector::parse(filter.labels.as_deref().unwrap_or_default()) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    info!(target: "audit", action = "list", labels = filter.labels, tags = filter.tags);
+    let tags = filter
+        .tags
+        .as_deref()
+        .unwrap_or_default()
+        .split(',')
+        .filter(|t| !t.is_empty())
+        .collect::<Vec<&str>>();
+    let mut tasks = state
+        .store
+        .lock()
+        .unwrap()
+        .tasks
+        .iter()
+        .filter(|t| selector.matches(&t.labels) && tags.iter().all(|tag| t.tags.contains(*tag)))
+        .cloned()
+        .collect::<Vec<Task>>();
+    for task in tasks.iter_mut() {
+        task.estimated_completion = task.estimate_completion();
+    }
+    if accepts_json(&headers) {
+        return Json(tasks).into_response();
+    }
     // bitcode::serialize(&tasks[0])
     //     .expect("tasks are serializable so it should not panic")
     //     .into_response()
@@ -104,18 +344,47 @@ async fn view_task(
     if authorized_status != StatusCode::OK {
         return authorized_status.into_response();
     }
-    if let Some(task) = state.tasks.lock().unwrap().iter().find(|t| t.id == uuid) {
+    if let Some(mut task) = task_with_uuid(&state, &uuid) {
+        task.estimated_completion = task.estimate_completion();
+        // the abort and update tokens are only given back to a client which already has the update privilege.
+        if !has_privilege(&headers, &state, ClientPrivilege::Update(uuid)) {
+            task.tokens = Default::default();
+        }
+        let etag = task.etag();
+        if accepts_json(&headers) {
+            return ([(ETAG, etag)], Json(task)).into_response();
+        }
         // bincode::serialize(&task)
         //     .expect("tasks are serializable so it should not panic")
         //     .into_response()
-        bincode::encode_to_vec(task, state.config_bincode)
-            .unwrap()
+        (
+            [(ETAG, etag)],
+            bincode::encode_to_vec(task, state.config_bincode).unwrap(),
+        )
             .into_response()
     } else {
         StatusCode::NOT_FOUND.into_response()
     }
 }
 
+// view a task from the external reference given at creation, unique in its scope.
+async fn view_task_by_ref(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+    Path((scope, external_ref)): Path<(String, String)>,
+) -> impl IntoResponse {
+    // an unknown reference is authorized like an id that matches no task, so only an admin learns that it does not exist.
+    let uuid = state
+        .store
+        .lock()
+        .unwrap()
+        .by_ref(&scope, &external_ref)
+        .map_or(Uuid::nil(), |t| t.id);
+    view_task(headers, State(state), Path(uuid))
+        .await
+        .into_response()
+}
+
 async fn create_task(
     headers: HeaderMap,
     State(state): State<AppState>,
@@ -126,35 +395,106 @@ async fn create_task(
     if authorized_status != StatusCode::OK {
         return authorized_status.into_response();
     }
+    // a retried request with the same Idempotency-Key and credential returns the task already created.
+    let credential = headers
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or_default();
+    let idempotency_key = match headers.get("Idempotency-Key").map(|v| v.to_str()) {
+        Some(Ok(key)) => Some(key),
+        Some(Err(_)) => return StatusCode::BAD_REQUEST.into_response(),
+        None => None,
+    };
     // get the body into a task
-    if let Ok((new_task, _)) =
-        bincode::decode_from_slice::<NewTask, Configuration>(&body, state.config_bincode)
-    {
-        let task = new_task.to_task();
-        let uuid = task.id;
-        let view_key = task.tokens.0.clone();
-        let abort_key = task.tokens.1.clone();
-        let update_key = task.tokens.2.clone();
-        let endpoint = format!("/tasks/{}", uuid);
-        // add task to the tasks in memory
-        state.tasks.lock().unwrap().push(task);
-        dbg!(&view_key);
-        (
-            StatusCode::CREATED,
-            AppendHeaders([
-                ("Content-Location", &endpoint),
-                ("ViewToken", &view_key),
-                ("AbortToken", &abort_key),
-                ("UpdateToken", &update_key),
-            ]),
-        )
-            .into_response()
+    if let Some(new_task) = decode_new_task(&body, state.config_bincode) {
+        match insert_task(&state, credential, idempotency_key, new_task) {
+            Ok((status, task)) => created_response(status, &task),
+            Err(status) => status.into_response(),
+        }
     } else {
         // body was malformed
         StatusCode::BAD_REQUEST.into_response()
     }
 }
 
+// add a new task to the store, the creation privilege must have been verified.
+// returns the task with the status CREATED, or OK if the task was already created with the same idempotency key.
+fn insert_task(
+    state: &AppState,
+    credential: &str,
+    idempotency_key: Option<&str>,
+    mut new_task: NewTask,
+) -> Result<(StatusCode, Task), StatusCode> {
+    let mut store = state.store.lock().unwrap();
+    if let Some(key) = idempotency_key {
+        if let Some(task) = state
+            .idempotency_keys
+            .lock()
+            .unwrap()
+            .get(credential, key)
+            .and_then(|uuid| store.get(&uuid))
+        {
+            return Ok((StatusCode::OK, task.clone()));
+        }
+    }
+    // the external reference must be unique in the scope.
+    if let Some(external_ref) = &new_task.external_ref {
+        if store.by_ref(&new_task.scope, external_ref).is_some() {
+            return Err(StatusCode::CONFLICT);
+        }
+    }
+    // a task with pending dependencies starts blocked.
+    let status = dependencies::initial_status(&store.tasks, &new_task.dependencies)?;
+    // the maximum lifetime of the server applies if the task has none or a longer one.
+    new_task.max_lifetime = match (new_task.max_lifetime, state.max_lifetime) {
+        (Some(lifetime), Some(max)) => Some(lifetime.min(max)),
+        (lifetime, max) => lifetime.or(max),
+    };
+    if let Some(max) = state.max_duration {
+        new_task.duration = new_task.duration.min(max);
+    }
+    let mut task = new_task.to_task();
+    if status == TaskStatus::Blocked {
+        task.started_at = None;
+        task.progress_history.clear();
+    }
+    task.status = status;
+    if let Some(key) = idempotency_key {
+        state
+            .idempotency_keys
+            .lock()
+            .unwrap()
+            .insert(credential, key, task.id);
+    }
+    // add task to the tasks in memory
+    let uuid = task.id;
+    store.insert(task.clone());
+    store.audit.record(
+        uuid,
+        "create",
+        String::new(),
+        task.status.as_str().to_string(),
+    );
+    store.schedule_lifetime(uuid);
+    METRICS.created.inc();
+    Ok((StatusCode::CREATED, task))
+}
+
+// response giving the location and the tokens of a task.
+fn created_response(status: StatusCode, task: &Task) -> Response {
+    let endpoint = format!("/tasks/{}", task.id);
+    (
+        status,
+        AppendHeaders([
+            ("Content-Location", &endpoint),
+            ("ViewToken", &task.tokens.0),
+            ("AbortToken", &task.tokens.1),
+            ("UpdateToken", &task.tokens.2),
+        ]),
+    )
+        .into_response()
+}
+
 // this endpoint can be used to update the progress or update the status to done or aborted.
 // we know if it's using token for deleting or updating
 async fn update_task(
@@ -174,18 +514,34 @@ async fn update_task(
         return authorized_status.into_response();
     }
     let using_delete = method == Method::DELETE;
+    if let Some(update) = decode_update(&body, state.config_bincode) {
+        apply_update(&state, &headers, uuid, update, using_delete)
+    } else {
+        StatusCode::BAD_REQUEST.into_response()
+    }
+}
+
+// apply an update to a task, the update or abort privilege must have been verified.
+// an update applied with the abort privilege can only abort the task.
+fn apply_update(
+    state: &AppState,
+    headers: &HeaderMap,
+    uuid: Uuid,
+    update: TaskUpdate,
+    using_delete: bool,
+) -> Response {
     // get the current task.
-    let state_to_modify = state.tasks.clone();
-    if let Some(current_task) = state
-        .tasks
-        .lock()
-        .unwrap()
-        .iter_mut()
-        .find(|t| t.id == uuid)
-    {
-        // update only if status is currently active. Finished tasks must not be updated.
+    let mut store = state.store.lock().unwrap();
+    if let Some(current_task) = store.get_mut(&uuid) {
+        let old_status = current_task.status.as_str();
+        let old_progress = current_task.progress;
+        // the update is refused if the client did not see the current revision of the task.
+        if !if_match(headers, current_task) {
+            return StatusCode::PRECONDITION_FAILED.into_response();
+        }
+        // update only if status is currently active or blocked. Finished tasks must not be updated.
         // return a header with allowed method for this endpoint.
-        if current_task.status != TaskStatus::Active {
+        if current_task.status == TaskStatus::Done || current_task.status == TaskStatus::Aborted {
             return (
                 StatusCode::METHOD_NOT_ALLOWED,
                 AppendHeaders([(ALLOW, "GET")]),
@@ -195,54 +551,506 @@ async fn update_task(
         // only one of progress or status can be updated at once.
         // if status is different, progress is ignored.
         // else, only progress is updated.
-        if let Ok(((progress, status, desc_finished, payload_finished), _)) =
-            bincode::decode_from_slice::<(u8, TaskStatus, Option<String>, Vec<u8>), Configuration>(
-                &body,
-                state.config_bincode,
-            )
-        {
-            if status == TaskStatus::Done && using_delete {
-                return StatusCode::UNAUTHORIZED.into_response();
+        let TaskUpdate {
+            progress,
+            status,
+            description_result: desc_finished,
+            payload_result: payload_finished,
+            progress_count,
+            step,
+            force,
+        } = update;
+        if status == TaskStatus::Done && using_delete {
+            return StatusCode::UNAUTHORIZED.into_response();
+        }
+        // a blocked task can only be aborted until its dependencies are done.
+        if current_task.status == TaskStatus::Blocked && status != TaskStatus::Aborted {
+            return StatusCode::CONFLICT.into_response();
+        }
+        match status {
+            TaskStatus::Done | TaskStatus::Aborted => {
+                // need to update task with new status
+                if let Some(desc_finished) = desc_finished {
+                    current_task.description_result = desc_finished;
+                }
+                if !payload_finished.is_empty() {
+                    current_task.payload_result = payload_finished;
+                }
+                let now = unix_timestamp();
+                if status == TaskStatus::Done {
+                    current_task.complete_steps();
+                    // a done task is at 100% like its steps, whatever the last progress reported.
+                    current_task.record_progress(100, now);
+                } else {
+                    current_task.touch(now);
+                }
+                let status_name = status.as_str();
+                current_task.status = status;
+                current_task.finished_at = Some(now);
+                // need to send a request informing that the task is done for each push address.
+                push_notification(current_task);
+                METRICS.finished(&current_task.status);
+                if current_task.status == TaskStatus::Aborted {
+                    info!(target: "audit", action = "abort", task = %uuid);
+                }
+                store.audit.record(
+                    uuid,
+                    "status",
+                    old_status.to_string(),
+                    status_name.to_string(),
+                );
+                // need to start a timer before retiring the task
+                store.schedule_forget(uuid);
+                // tasks depending on this one can now be unblocked or aborted.
+                dependencies::resolve_dependents(&mut store, uuid);
             }
-            match status {
-                TaskStatus::Done | TaskStatus::Aborted => {
-                    // need to update task with new status
-                    if let Some(desc_finished) = desc_finished {
-                        current_task.description_result = desc_finished;
-                    }
-                    if !payload_finished.is_empty() {
-                        current_task.payload_result = payload_finished;
-                    }
-                    current_task.status = status;
-                    // need to send a request informing that the task is done for each push address.
-                    let client = reqwest::Client::new();
-                    for adr in current_task.push_address.iter() {
-                        let adr = adr.clone();
-                        let client = client.clone();
-                        spawn(async move {
-                            let _ = client.get(adr).send().await;
-                        });
-                    }
-                    // need to start a timer before retiring the task
-                    let seconds = current_task.duration;
-                    spawn(async move {
-                        sleep(Duration::from_secs(seconds.into())).await;
-                        state_to_modify.lock().unwrap().retain(|t| t.id != uuid)
-                    });
+            // the status can not be set back to blocked.
+            TaskStatus::Blocked => return StatusCode::BAD_REQUEST.into_response(),
+            // if Status is Active, the progress must have been updated.
+            TaskStatus::Active => {
+                if using_delete {
+                    return StatusCode::UNAUTHORIZED.into_response();
                 }
-                // if Status is Active, the progress must have been updated.
-                _ => {
-                    if using_delete {
-                        return StatusCode::UNAUTHORIZED.into_response();
+                // the progress in % can be derived from the running step.
+                let progress = match step {
+                    Some((index, step_progress)) => {
+                        match current_task.steps_progress(index as usize, step_progress) {
+                            Some(steps_progress) => steps_progress,
+                            None => return StatusCode::BAD_REQUEST.into_response(),
+                        }
                     }
-                    current_task.progress = progress;
+                    None => progress,
+                };
+                // a progress count takes precedence over the progress in %.
+                let progress = progress_count
+                    .as_ref()
+                    .map_or(progress, ProgressCount::percentage);
+                if state.monotonic_progress && !force && progress < current_task.progress {
+                    return StatusCode::CONFLICT.into_response();
                 }
-            };
-            StatusCode::ACCEPTED.into_response()
-        } else {
-            StatusCode::BAD_REQUEST.into_response()
-        }
+                if let Some((index, step_progress)) = step {
+                    current_task.set_current_step(index as usize, step_progress);
+                }
+                current_task.progress_count = progress_count;
+                current_task.record_progress(progress, unix_timestamp());
+                METRICS.updated.inc();
+                store.audit.record(
+                    uuid,
+                    "progress",
+                    old_progress.to_string(),
+                    progress.to_string(),
+                );
+            }
+        };
+        let etag = store.get(&uuid).map(Task::etag).unwrap_or_default();
+        (StatusCode::ACCEPTED, [(ETAG, etag)]).into_response()
     } else {
         StatusCode::NOT_FOUND.into_response()
     }
 }
+
+// forget a finished task immediately instead of waiting for its duration, with the update token.
+// unlike DELETE on the task which aborts it, the task and its result are removed from memory.
+async fn purge_task(
+    headers: HeaderMap,
+    Path(uuid): Path<Uuid>,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let authorized_status = is_authorized(&headers, &state, ClientPrivilege::Update(uuid));
+    if authorized_status != StatusCode::OK {
+        return authorized_status.into_response();
+    }
+    let mut store = state.store.lock().unwrap();
+    let Some(task) = store.get(&uuid) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if !if_match(&headers, task) {
+        return StatusCode::PRECONDITION_FAILED.into_response();
+    }
+    // the task must be finished first, by aborting it if needed.
+    if task.status == TaskStatus::Active || task.status == TaskStatus::Blocked {
+        return StatusCode::CONFLICT.into_response();
+    }
+    // the pending timer to forget the task is cancelled.
+    store.remove(&uuid);
+    info!(target: "audit", action = "purge", task = %uuid);
+    StatusCode::NO_CONTENT.into_response()
+}
+
+// change how long a task is kept after it finished, with the update token.
+// the body is (Option<u32>, Option<bool>): the new duration in seconds and whether the task is pinned, which requires the admin token.
+// a pinned task is never forgotten automatically.
+async fn set_retention(
+    headers: HeaderMap,
+    Path(uuid): Path<Uuid>,
+    State(state): State<AppState>,
+    body: Bytes,
+) -> impl IntoResponse {
+    let authorized_status = is_authorized(&headers, &state, ClientPrivilege::Update(uuid));
+    if authorized_status != StatusCode::OK {
+        return authorized_status.into_response();
+    }
+    let Ok(((duration, pinned), _)) = bincode::decode_from_slice::<
+        (Option<u32>, Option<bool>),
+        Configuration,
+    >(&body, state.config_bincode) else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+    if pinned.is_some() {
+        let authorized_status = is_authorized(&headers, &state, ClientPrivilege::Admin);
+        if authorized_status != StatusCode::OK {
+            return authorized_status.into_response();
+        }
+    }
+    let mut store = state.store.lock().unwrap();
+    let Some(task) = store.get_mut(&uuid) else {
+        return StatusCode::NOT_FOUND.into_response();
+    };
+    if !if_match(&headers, task) {
+        return StatusCode::PRECONDITION_FAILED.into_response();
+    }
+    let old_retention = format!("duration {} pinned {}", task.duration, task.pinned);
+    if let Some(duration) = duration {
+        task.duration = state.max_duration.map_or(duration, |max| duration.min(max));
+    }
+    if let Some(pinned) = pinned {
+        task.pinned = pinned;
+    }
+    task.touch(unix_timestamp());
+    let new_retention = format!("duration {} pinned {}", task.duration, task.pinned);
+    info!(
+        target: "audit",
+        action = "retention",
+        task = %uuid,
+        duration = task.duration,
+        pinned = task.pinned
+    );
+    let etag = task.etag();
+    store
+        .audit
+        .record(uuid, "retention", old_retention, new_retention);
+    // the timer of a finished task is rescheduled with the new retention.
+    store.schedule_forget(uuid);
+    (StatusCode::ACCEPTED, [(ETAG, etag)]).into_response()
+}
+
+// decode the body of an update request.
+// the tuple sent by previous versions of the client is still accepted.
+fn decode_update(body: &[u8], config: Configuration) -> Option<TaskUpdate> {
+    if let Ok((update, _)) = bincode::decode_from_slice::<TaskUpdate, Configuration>(body, config) {
+        return Some(update);
+    }
+    bincode::decode_from_slice::<(u8, TaskStatus, Option<String>, Vec<u8>), Configuration>(
+        body, config,
+    )
+    .ok()
+    .map(
+        |((progress, status, description_result, payload_result), _)| TaskUpdate {
+            progress,
+            status,
+            description_result,
+            payload_result,
+            progress_count: None,
+            step: None,
+            force: false,
+        },
+    )
+}
+
+// fields of a new task sent by previous versions of the client, without the options added since.
+#[derive(bincode::Decode)]
+#[cfg_attr(test, derive(bincode::Encode))]
+struct LegacyNewTask {
+    duration: u32,
+    scope: String,
+    name: String,
+    description: String,
+    #[bincode(with_serde)]
+    push_address: Vec<reqwest::Url>,
+    payload: Vec<u8>,
+}
+
+// decode the body of a creation request.
+// the new task sent by previous versions of the client is still accepted, with the default options.
+fn decode_new_task(body: &[u8], config: Configuration) -> Option<NewTask> {
+    if let Ok((new_task, _)) = bincode::decode_from_slice::<NewTask, Configuration>(body, config) {
+        return Some(new_task);
+    }
+    bincode::decode_from_slice::<LegacyNewTask, Configuration>(body, config)
+        .ok()
+        .map(|(legacy, _)| NewTask {
+            duration: legacy.duration,
+            scope: legacy.scope,
+            name: legacy.name,
+            description: legacy.description,
+            push_address: legacy.push_address,
+            payload: legacy.payload,
+            ..NewTask::default()
+        })
+}
+
+// the json form of the tasks is used by the dashboard, other clients use bincode.
+pub(crate) fn accepts_json(headers: &HeaderMap) -> bool {
+    headers
+        .get(ACCEPT)
+        .and_then(|accept| accept.to_str().ok())
+        .is_some_and(|accept| accept.contains("application/json"))
+}
+
+// check the If-Match header against the ETag of the task.
+// returns true if there is no such header. the comparison is strong, a weak tag never matches (RFC 9110 13.1.1).
+pub(crate) fn if_match(headers: &HeaderMap, task: &Task) -> bool {
+    let Some(value) = headers.get(IF_MATCH) else {
+        return true;
+    };
+    let etag = task.etag();
+    value.to_str().is_ok_and(|value| {
+        value
+            .split(',')
+            .map(str::trim)
+            .any(|tag| tag == "*" || tag == etag)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+    use tasks_tracker_common::{ProgressCount, BINCODE_CONFIG};
+
+    use super::*;
+
+    // task at the given revision, its ETag is "revision".
+    fn task(revision: u64) -> Task {
+        let mut task = NewTask::default().to_task();
+        task.revision = revision;
+        task
+    }
+
+    fn if_match_header(value: HeaderValue) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(IF_MATCH, value);
+        headers
+    }
+
+    pub(crate) fn state() -> AppState {
+        AppState {
+            store: Arc::default(),
+            token_admin: Some("admin".to_string()),
+            token_create: "create".to_string(),
+            config_bincode: BINCODE_CONFIG,
+            idempotency_keys: Arc::new(Mutex::new(IdempotencyKeys::new(86400))),
+            monotonic_progress: false,
+            max_lifetime: None,
+            max_duration: None,
+            max_notification_backlog: 1000,
+        }
+    }
+
+    fn bearer(token: &str) -> HeaderValue {
+        HeaderValue::from_str(&["Bearer ", token].concat()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn view_task_hides_the_tokens_without_the_update_privilege() {
+        let state = state();
+        let task = task(0);
+        let (uuid, tokens) = (task.id, task.tokens.clone());
+        state.store.lock().unwrap().insert(task);
+        let server = axum_test::TestServer::new(routes(state)).unwrap();
+        let view = |token: &str| {
+            server
+                .get(&format!("/tasks/{}", uuid))
+                .add_header(AUTHORIZATION, bearer(token))
+        };
+        let decode = |body: &[u8]| {
+            bincode::decode_from_slice::<Task, Configuration>(body, BINCODE_CONFIG)
+                .unwrap()
+                .0
+        };
+        let response = view(&tokens.0).await;
+        response.assert_status_ok();
+        assert_eq!(decode(response.as_bytes()).tokens, Default::default());
+        let response = view("admin").await;
+        assert_eq!(decode(response.as_bytes()).tokens, tokens);
+    }
+
+    #[tokio::test]
+    async fn create_task_with_a_repeated_idempotency_key_returns_the_same_task() {
+        let server = axum_test::TestServer::new(routes(state())).unwrap();
+        let body = bincode::encode_to_vec(NewTask::default(), BINCODE_CONFIG).unwrap();
+        let create = |token: &str| {
+            server
+                .post("/tasks")
+                .add_header(AUTHORIZATION, bearer(token))
+                .add_header(
+                    "Idempotency-Key".parse().unwrap(),
+                    HeaderValue::from_static("key"),
+                )
+                .bytes(body.clone().into())
+        };
+        let first = create("create").await;
+        first.assert_status(StatusCode::CREATED);
+        let retry = create("create").await;
+        retry.assert_status_ok();
+        for header in ["Content-Location", "ViewToken", "AbortToken", "UpdateToken"] {
+            assert_eq!(first.header(header), retry.header(header));
+        }
+        // the same key with another credential creates another task.
+        let other = create("admin").await;
+        other.assert_status(StatusCode::CREATED);
+        assert_ne!(
+            first.header("Content-Location"),
+            other.header("Content-Location")
+        );
+    }
+
+    #[test]
+    fn if_match_without_header() {
+        assert!(if_match(&HeaderMap::new(), &task(3)));
+    }
+
+    #[test]
+    fn if_match_compares_the_current_revision() {
+        let headers = if_match_header(HeaderValue::from_static("\"3\""));
+        assert!(if_match(&headers, &task(3)));
+        assert!(!if_match(&headers, &task(4)));
+    }
+
+    #[test]
+    fn if_match_accepts_any_revision_with_a_star() {
+        let headers = if_match_header(HeaderValue::from_static("*"));
+        assert!(if_match(&headers, &task(0)));
+        assert!(if_match(&headers, &task(7)));
+    }
+
+    #[test]
+    fn if_match_accepts_a_list_of_tags() {
+        let headers = if_match_header(HeaderValue::from_static("\"1\", \"3\",\"5\""));
+        assert!(if_match(&headers, &task(3)));
+        assert!(if_match(&headers, &task(5)));
+        assert!(!if_match(&headers, &task(2)));
+    }
+
+    #[test]
+    fn if_match_never_matches_a_weak_tag() {
+        let headers = if_match_header(HeaderValue::from_static("W/\"3\""));
+        assert!(!if_match(&headers, &task(3)));
+        let headers = if_match_header(HeaderValue::from_static("W/\"3\", \"4\""));
+        assert!(!if_match(&headers, &task(3)));
+        assert!(if_match(&headers, &task(4)));
+    }
+
+    #[test]
+    fn if_match_rejects_a_header_which_is_not_text() {
+        let headers = if_match_header(HeaderValue::from_bytes(b"\"3\"\xff").unwrap());
+        assert!(!if_match(&headers, &task(3)));
+    }
+
+    #[test]
+    fn decode_update_accepts_the_legacy_tuple() {
+        let body = bincode::encode_to_vec(
+            (
+                100u8,
+                TaskStatus::Done,
+                Some("result".to_string()),
+                vec![1u8, 2, 3],
+            ),
+            BINCODE_CONFIG,
+        )
+        .unwrap();
+        let update = decode_update(&body, BINCODE_CONFIG).unwrap();
+        assert_eq!(update.progress, 100);
+        assert!(update.status == TaskStatus::Done);
+        assert_eq!(update.description_result.as_deref(), Some("result"));
+        assert_eq!(update.payload_result, vec![1, 2, 3]);
+        assert!(update.progress_count.is_none());
+        assert_eq!(update.step, None);
+        assert!(!update.force);
+    }
+
+    #[test]
+    fn decode_update_accepts_the_legacy_progress() {
+        let body = bincode::encode_to_vec(
+            (40u8, TaskStatus::Active, None::<String>, Vec::<u8>::new()),
+            BINCODE_CONFIG,
+        )
+        .unwrap();
+        let update = decode_update(&body, BINCODE_CONFIG).unwrap();
+        assert_eq!(update.progress, 40);
+        assert!(update.status == TaskStatus::Active);
+        assert_eq!(update.description_result, None);
+        assert!(update.payload_result.is_empty());
+    }
+
+    #[test]
+    fn decode_update_accepts_the_struct() {
+        let update = TaskUpdate {
+            force: true,
+            ..TaskUpdate::progress_count(3, 4, "files".to_string())
+        };
+        let body = bincode::encode_to_vec(update, BINCODE_CONFIG).unwrap();
+        let update = decode_update(&body, BINCODE_CONFIG).unwrap();
+        assert!(update.status == TaskStatus::Active);
+        assert!(
+            update.progress_count
+                == Some(ProgressCount {
+                    current: 3,
+                    total: 4,
+                    unit: "files".to_string(),
+                })
+        );
+        assert!(update.force);
+
+        let body = bincode::encode_to_vec(TaskUpdate::step(2, 50), BINCODE_CONFIG).unwrap();
+        let update = decode_update(&body, BINCODE_CONFIG).unwrap();
+        assert_eq!(update.step, Some((2, 50)));
+        assert!(!update.force);
+    }
+
+    #[test]
+    fn decode_new_task_accepts_the_legacy_fields() {
+        let legacy = LegacyNewTask {
+            duration: 60,
+            scope: "scope".to_string(),
+            name: "name".to_string(),
+            description: "description".to_string(),
+            push_address: vec!["http://127.0.0.1:9000/notify".parse().unwrap()],
+            payload: vec![1, 2, 3],
+        };
+        let body = bincode::encode_to_vec(legacy, BINCODE_CONFIG).unwrap();
+        let new_task = decode_new_task(&body, BINCODE_CONFIG).unwrap();
+        assert_eq!(new_task.duration, 60);
+        assert_eq!(new_task.scope, "scope");
+        assert_eq!(new_task.name, "name");
+        assert_eq!(new_task.description, "description");
+        assert_eq!(new_task.push_address.len(), 1);
+        assert_eq!(new_task.payload, vec![1, 2, 3]);
+        assert!(new_task.dependencies.is_empty());
+        assert!(new_task.steps.is_empty());
+        assert!(new_task.labels.is_empty());
+        assert_eq!(new_task.external_ref, None);
+        assert_eq!(new_task.max_lifetime, None);
+    }
+
+    #[test]
+    fn decode_new_task_accepts_the_struct() {
+        let new_task = NewTask {
+            name: "name".to_string(),
+            steps: vec!["download".to_string()],
+            external_ref: Some("order-1".to_string()),
+            max_lifetime: Some(600),
+            ..NewTask::default()
+        };
+        let body = bincode::encode_to_vec(new_task, BINCODE_CONFIG).unwrap();
+        let new_task = decode_new_task(&body, BINCODE_CONFIG).unwrap();
+        assert_eq!(new_task.steps, vec!["download".to_string()]);
+        assert_eq!(new_task.external_ref.as_deref(), Some("order-1"));
+        assert_eq!(new_task.max_lifetime, Some(600));
+    }
+
+    #[test]
+    fn decode_update_rejects_a_truncated_body() {
+        let body = bincode::encode_to_vec(TaskUpdate::progress(10), BINCODE_CONFIG).unwrap();
+        assert!(decode_update(&body[..1], BINCODE_CONFIG).is_none());
+    }
+}
@@ -0,0 +1,175 @@
+use std::{
+    collections::HashMap,
+    sync::{LazyLock, Mutex},
+    time::Instant,
+};
+
+use axum::{
+    extract::{MatchedPath, Request, State},
+    http::{header::CONTENT_TYPE, HeaderMap, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use prometheus::{
+    core::Collector, Encoder, HistogramOpts, HistogramVec, IntCounter, IntCounterVec, IntGaugeVec,
+    Opts, Registry, TextEncoder,
+};
+use tasks_tracker_common::TaskStatus;
+
+use crate::{authorize::is_authorized, AppState, ClientPrivilege};
+
+/// metrics of the server, exposed in the prometheus text format.
+pub(crate) struct Metrics {
+    registry: Registry,
+    // live tasks by status and scope, computed from the store when scraped.
+    tasks: IntGaugeVec,
+    pub(crate) created: IntCounter,
+    pub(crate) updated: IntCounter,
+    // finished tasks by status.
+    finished: IntCounterVec,
+    // tasks expired by reason, lifetime for aborted unfinished tasks, retention for forgotten finished tasks.
+    expired: IntCounterVec,
+    // refused authorizations by status code.
+    auth_failures: IntCounterVec,
+    // push notifications by outcome.
+    notifications: IntCounterVec,
+    // latency of requests by route and method.
+    requests: HistogramVec,
+    // serializes the scrapes, the gauge of the tasks is reset then refilled by each of them.
+    scrape: Mutex<()>,
+}
+
+pub(crate) static METRICS: LazyLock<Metrics> = LazyLock::new(Metrics::new);
+
+impl Metrics {
+    fn new() -> Self {
+        let registry = Registry::new_custom(Some("tasks_tracker".to_string()), None)
+            .expect("prefix is valid so it should not panic");
+        let metrics = Metrics {
+            tasks: IntGaugeVec::new(
+                Opts::new("tasks", "tasks in memory by status and scope"),
+                &["status", "scope"],
+            )
+            .unwrap(),
+            created: IntCounter::new("tasks_created_total", "tasks created").unwrap(),
+            updated: IntCounter::new("tasks_updated_total", "progress updates of tasks").unwrap(),
+            finished: IntCounterVec::new(
+                Opts::new("tasks_finished_total", "tasks finished by status"),
+                &["status"],
+            )
+            .unwrap(),
+            expired: IntCounterVec::new(
+                Opts::new("tasks_expired_total", "tasks expired by reason"),
+                &["reason"],
+            )
+            .unwrap(),
+            auth_failures: IntCounterVec::new(
+                Opts::new(
+                    "auth_failures_total",
+                    "refused authorizations by status code",
+                ),
+                &["status"],
+            )
+            .unwrap(),
+            notifications: IntCounterVec::new(
+                Opts::new("push_notifications_total", "push notifications by outcome"),
+                &["outcome"],
+            )
+            .unwrap(),
+            requests: HistogramVec::new(
+                HistogramOpts::new(
+                    "http_request_duration_seconds",
+                    "latency of requests by route",
+                ),
+                &["route", "method"],
+            )
+            .unwrap(),
+            registry,
+            scrape: Mutex::new(()),
+        };
+        let collectors: [Box<dyn Collector>; 8] = [
+            Box::new(metrics.tasks.clone()),
+            Box::new(metrics.created.clone()),
+            Box::new(metrics.updated.clone()),
+            Box::new(metrics.finished.clone()),
+            Box::new(metrics.expired.clone()),
+            Box::new(metrics.auth_failures.clone()),
+            Box::new(metrics.notifications.clone()),
+            Box::new(metrics.requests.clone()),
+        ];
+        for collector in collectors {
+            metrics
+                .registry
+                .register(collector)
+                .expect("metrics have distinct names so it should not panic");
+        }
+        metrics
+    }
+    pub(crate) fn finished(&self, status: &TaskStatus) {
+        self.finished.with_label_values(&[status.as_str()]).inc();
+    }
+    pub(crate) fn expired(&self, reason: &str) {
+        self.expired.with_label_values(&[reason]).inc();
+    }
+    pub(crate) fn auth_failure(&self, status: StatusCode) {
+        self.auth_failures
+            .with_label_values(&[status.as_str()])
+            .inc();
+    }
+    pub(crate) fn notification(&self, delivered: bool) {
+        self.notifications
+            .with_label_values(&[if delivered { "delivered" } else { "failed" }])
+            .inc();
+    }
+}
+
+/// middleware recording the latency of each request.
+pub(crate) async fn track_latency(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or("unmatched", MatchedPath::as_str)
+        .to_string();
+    let method = request.method().to_string();
+    let start = Instant::now();
+    let response = next.run(request).await;
+    METRICS
+        .requests
+        .with_label_values(&[&route, &method])
+        .observe(start.elapsed().as_secs_f64());
+    response
+}
+
+/// expose the metrics in the prometheus text format, with the admin token.
+pub(crate) async fn metrics(
+    headers: HeaderMap,
+    State(state): State<AppState>,
+) -> impl IntoResponse {
+    let authorized_status = is_authorized(&headers, &state, ClientPrivilege::Admin);
+    if authorized_status != StatusCode::OK {
+        return authorized_status.into_response();
+    }
+    let mut counts = HashMap::<_, i64>::new();
+    for task in state.store.lock().unwrap().tasks.iter() {
+        *counts
+            .entry((task.status.as_str(), task.scope.clone()))
+            .or_default() += 1;
+    }
+    let families = {
+        let _scrape = METRICS.scrape.lock().unwrap();
+        METRICS.tasks.reset();
+        for ((status, scope), count) in counts {
+            METRICS
+                .tasks
+                .with_label_values(&[status, &scope])
+                .set(count);
+        }
+        METRICS.registry.gather()
+    };
+    let encoder = TextEncoder::new();
+    let mut buffer = Vec::new();
+    encoder
+        .encode(&families, &mut buffer)
+        .expect("metrics are encodable so it should not panic");
+    ([(CONTENT_TYPE, encoder.format_type().to_string())], buffer).into_response()
+}
@@ -0,0 +1,76 @@
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+    time::Duration,
+};
+
+use reqwest::header::CONTENT_TYPE;
+use tasks_tracker_common::{LogEntry, Notification, Task, BINCODE_CONFIG};
+use tokio::spawn;
+
+use crate::metrics::METRICS;
+
+// number of push notifications sent and not yet answered.
+static PENDING: AtomicUsize = AtomicUsize::new(0);
+
+pub(crate) fn pending_notifications() -> usize {
+    PENDING.load(Ordering::Relaxed)
+}
+
+// client sending the notifications, with the timeout of the server settings.
+static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+
+// must be called before the first notification, the default timeout of reqwest applies otherwise.
+pub(crate) fn init(timeout: Duration) {
+    let client = reqwest::Client::builder()
+        .timeout(timeout)
+        .build()
+        .expect("client without tls can always be built so it should not panic");
+    let _ = CLIENT.set(client);
+}
+
+/// push notifications to the listening addresses of a task.
+// a POST request is sent to each push address of the task, without waiting for the response.
+// the body of the request is the bincode encoded Notification of the task.
+pub(crate) fn push_notification(task: &Task) {
+    send(task, Notification::from(task));
+}
+
+/// push a notification with the entry appended to the log of a task.
+pub(crate) fn push_log_entry(task: &Task, entry: &LogEntry) {
+    send(
+        task,
+        Notification {
+            log_entry: Some(entry.clone()),
+            ..Notification::from(task)
+        },
+    );
+}
+
+fn send(task: &Task, notification: Notification) {
+    if task.push_address.is_empty() {
+        return;
+    }
+    let body = bincode::encode_to_vec(notification, BINCODE_CONFIG)
+        .expect("notification is encodable so it should not panic");
+    let client = CLIENT.get_or_init(reqwest::Client::new);
+    for adr in task.push_address.iter() {
+        let adr = adr.clone();
+        let client = client.clone();
+        let body = body.clone();
+        PENDING.fetch_add(1, Ordering::Relaxed);
+        spawn(async move {
+            let delivered = client
+                .post(adr)
+                .header(CONTENT_TYPE, "application/octet-stream")
+                .body(body)
+                .send()
+                .await
+                .is_ok_and(|rep| rep.status().is_success());
+            METRICS.notification(delivered);
+            PENDING.fetch_sub(1, Ordering::Relaxed);
+        });
+    }
+}
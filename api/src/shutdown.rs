@@ -0,0 +1,68 @@
+use std::{sync::Arc, time::Duration};
+
+use tokio::{
+    select,
+    signal::{
+        ctrl_c,
+        unix::{signal, SignalKind},
+    },
+    spawn,
+    sync::watch,
+    time::{sleep, sleep_until, Instant},
+};
+use tracing::{info, warn};
+
+use crate::notify::pending_notifications;
+
+// interval at which the notifications in flight are counted while draining.
+const DRAIN_INTERVAL: Duration = Duration::from_millis(100);
+
+/// shutdown of the server, requested by SIGINT or SIGTERM.
+// the deadline until which requests and notifications in flight are awaited is set when it is requested.
+#[derive(Clone)]
+pub(crate) struct Shutdown(Arc<watch::Sender<Option<Instant>>>);
+
+impl Shutdown {
+    pub(crate) fn on_signal(grace: Duration) -> Self {
+        let shutdown = Shutdown(Arc::new(watch::channel(None).0));
+        let sender = shutdown.0.clone();
+        spawn(async move {
+            let mut terminate =
+                signal(SignalKind::terminate()).expect("SIGTERM handler must be installable");
+            select! {
+                _ = ctrl_c() => {},
+                _ = terminate.recv() => {},
+            }
+            info!("shutting down, waiting up to {} seconds", grace.as_secs());
+            sender.send_replace(Some(Instant::now() + grace));
+        });
+        shutdown
+    }
+    // resolves with the deadline once the shutdown is requested.
+    pub(crate) async fn requested(&self) -> Instant {
+        let mut receiver = self.0.subscribe();
+        let deadline = *receiver
+            .wait_for(Option::is_some)
+            .await
+            .expect("the sender is owned by self so it should not be dropped");
+        deadline.expect("waited for a deadline")
+    }
+    // resolves once the deadline of a requested shutdown is reached.
+    pub(crate) async fn deadline(&self) {
+        sleep_until(self.requested().await).await;
+    }
+}
+
+/// wait for the push notifications in flight to be sent, until the deadline.
+pub(crate) async fn drain_notifications(deadline: Instant) {
+    while pending_notifications() > 0 {
+        if Instant::now() >= deadline {
+            warn!(
+                "{} push notifications in flight are dropped",
+                pending_notifications()
+            );
+            return;
+        }
+        sleep(DRAIN_INTERVAL).await;
+    }
+}
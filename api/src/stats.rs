@@ -0,0 +1,221 @@
+use std::mem::size_of;
+
+use axum::{
+    extract::State,
+    http::{HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use tasks_tracker_common::{ScopeStats, Stats, Task, TaskStatus};
+
+use crate::{accepts_json, authorize::is_authorized, AppState, ClientPrivilege};
+
+/// aggregated figures of the tasks, with the admin token.
+// encoded in bincode, or in json if the client accepts it.
+pub(crate) async fn stats(headers: HeaderMap, State(state): State<AppState>) -> Response {
+    let authorized_status = is_authorized(&headers, &state, ClientPrivilege::Admin);
+    if authorized_status != StatusCode::OK {
+        return authorized_status.into_response();
+    }
+    let stats = compute(&state.store.lock().unwrap().tasks);
+    if accepts_json(&headers) {
+        return Json(stats).into_response();
+    }
+    bincode::encode_to_vec(stats, state.config_bincode)
+        .unwrap()
+        .into_response()
+}
+
+fn compute(tasks: &[Task]) -> Stats {
+    let mut stats = Stats {
+        tasks: tasks.len() as u64,
+        by_status: Default::default(),
+        by_scope: Default::default(),
+        average_duration: None,
+        duration_p50: None,
+        duration_p90: None,
+        duration_p99: None,
+        oldest_active: None,
+        memory_estimate: 0,
+    };
+    let mut durations = Vec::new();
+    for task in tasks {
+        let status = task.status.as_str().to_string();
+        *stats.by_status.entry(status.clone()).or_default() += 1;
+        *stats
+            .by_scope
+            .entry(task.scope.clone())
+            .or_default()
+            .by_status
+            .entry(status)
+            .or_default() += 1;
+        if let Some(finished_at) = task.finished_at {
+            durations.push(finished_at.saturating_sub(task.created_at));
+        }
+        if task.status == TaskStatus::Active
+            && stats
+                .oldest_active
+                .is_none_or(|(_, created_at)| task.created_at < created_at)
+        {
+            stats.oldest_active = Some((task.id, task.created_at));
+        }
+        stats.memory_estimate += memory_estimate(task);
+    }
+    for scope in stats.by_scope.values_mut() {
+        scope.abort_ratio = abort_ratio(scope);
+    }
+    if !durations.is_empty() {
+        durations.sort_unstable();
+        stats.average_duration =
+            Some(durations.iter().sum::<u64>() as f64 / durations.len() as f64);
+        stats.duration_p50 = Some(percentile(&durations, 50));
+        stats.duration_p90 = Some(percentile(&durations, 90));
+        stats.duration_p99 = Some(percentile(&durations, 99));
+    }
+    stats
+}
+
+fn abort_ratio(scope: &ScopeStats) -> Option<f64> {
+    let count = |status: &TaskStatus| scope.by_status.get(status.as_str()).copied();
+    let aborted = count(&TaskStatus::Aborted).unwrap_or_default();
+    let finished = aborted + count(&TaskStatus::Done).unwrap_or_default();
+    (finished != 0).then(|| aborted as f64 / finished as f64)
+}
+
+// nearest-rank percentile of sorted values.
+fn percentile(sorted: &[u64], percent: usize) -> u64 {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+// size of the task and of the data it owns on the heap, ignoring allocator overhead.
+fn memory_estimate(task: &Task) -> u64 {
+    let strings = task.scope.len()
+        + task.name.len()
+        + task.description.len()
+        + task.description_result.len()
+        + task.tokens.0.len()
+        + task.tokens.1.len()
+        + task.tokens.2.len()
+        + task.external_ref.as_ref().map_or(0, String::len)
+        + task
+            .push_address
+            .iter()
+            .map(|url| url.as_str().len())
+            .sum::<usize>()
+        + task
+            .labels
+            .iter()
+            .map(|(key, value)| key.len() + value.len())
+            .sum::<usize>()
+        + task.tags.iter().map(String::len).sum::<usize>();
+    let buffers = task.payload.len()
+        + task.payload_result.len()
+        + task.dependencies.len() * size_of::<uuid::Uuid>()
+        + task.progress_history.len() * size_of::<(u64, u8)>()
+        + task
+            .log
+            .iter()
+            .map(|entry| size_of_val(entry) + entry.message.len())
+            .sum::<usize>()
+        + task
+            .steps
+            .iter()
+            .map(|step| size_of_val(step) + step.name.len())
+            .sum::<usize>();
+    (size_of::<Task>() + strings + buffers) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use tasks_tracker_common::NewTask;
+
+    use super::*;
+
+    fn task(scope: &str, status: TaskStatus) -> Task {
+        let mut task = NewTask {
+            scope: scope.to_string(),
+            ..NewTask::default()
+        }
+        .to_task();
+        if status == TaskStatus::Done || status == TaskStatus::Aborted {
+            task.finished_at = Some(task.created_at + 10);
+        }
+        task.status = status;
+        task
+    }
+
+    #[test]
+    fn percentile_of_one_value() {
+        for percent in [50, 90, 99] {
+            assert_eq!(percentile(&[7], percent), 7);
+        }
+    }
+
+    #[test]
+    fn percentile_is_the_nearest_rank() {
+        let values = (1..=10).collect::<Vec<u64>>();
+        assert_eq!(percentile(&values, 50), 5);
+        assert_eq!(percentile(&values, 90), 9);
+        assert_eq!(percentile(&values, 99), 10);
+        let values = (1..=100).collect::<Vec<u64>>();
+        assert_eq!(percentile(&values, 50), 50);
+        assert_eq!(percentile(&values, 90), 90);
+        assert_eq!(percentile(&values, 99), 99);
+        // one more value moves every rank up.
+        let values = (1..=101).collect::<Vec<u64>>();
+        assert_eq!(percentile(&values, 50), 51);
+        assert_eq!(percentile(&values, 90), 91);
+        assert_eq!(percentile(&values, 99), 100);
+    }
+
+    #[test]
+    fn abort_ratio_over_the_finished_tasks() {
+        let scope = |counts: &[(&TaskStatus, u64)]| ScopeStats {
+            by_status: counts
+                .iter()
+                .map(|(status, count)| (status.as_str().to_string(), *count))
+                .collect(),
+            abort_ratio: None,
+        };
+        assert_eq!(abort_ratio(&scope(&[])), None);
+        assert_eq!(abort_ratio(&scope(&[(&TaskStatus::Active, 3)])), None);
+        assert_eq!(
+            abort_ratio(&scope(&[
+                (&TaskStatus::Aborted, 1),
+                (&TaskStatus::Done, 3),
+                (&TaskStatus::Active, 4)
+            ])),
+            Some(0.25)
+        );
+        assert_eq!(abort_ratio(&scope(&[(&TaskStatus::Aborted, 2)])), Some(1.0));
+    }
+
+    #[test]
+    fn compute_without_finished_tasks() {
+        let stats = compute(&[
+            task("running", TaskStatus::Active),
+            task("running", TaskStatus::Blocked),
+        ]);
+        assert_eq!(stats.tasks, 2);
+        assert_eq!(stats.by_scope["running"].abort_ratio, None);
+        assert_eq!(stats.average_duration, None);
+        assert_eq!(stats.duration_p50, None);
+        assert_eq!(stats.duration_p99, None);
+        assert!(stats.oldest_active.is_some());
+    }
+
+    #[test]
+    fn compute_by_scope() {
+        let stats = compute(&[
+            task("running", TaskStatus::Active),
+            task("finished", TaskStatus::Done),
+            task("finished", TaskStatus::Aborted),
+        ]);
+        assert_eq!(stats.by_status["active"], 1);
+        assert_eq!(stats.by_scope["running"].abort_ratio, None);
+        assert_eq!(stats.by_scope["finished"].abort_ratio, Some(0.5));
+        assert_eq!(stats.average_duration, Some(10.0));
+        assert_eq!(stats.duration_p50, Some(10));
+    }
+}
@@ -0,0 +1,135 @@
+use std::collections::{HashMap, HashSet};
+
+use tasks_tracker_common::{unix_timestamp, Task, TaskStatus};
+use uuid::Uuid;
+
+use crate::{
+    audit::AuditTrail, dependencies, expiry::Expiry, metrics::METRICS, notify::push_notification,
+};
+
+/// tasks kept in memory, with the indexes to find them.
+#[derive(Default)]
+pub(crate) struct Store {
+    pub(crate) tasks: Vec<Task>,
+    // (scope, external reference) => id of the task.
+    refs: HashMap<(String, String), Uuid>,
+    // deadlines after which finished tasks are forgotten.
+    pub(crate) expiry: Expiry,
+    // changes made to each task.
+    pub(crate) audit: AuditTrail,
+}
+
+impl Store {
+    pub(crate) fn get(&self, uuid: &Uuid) -> Option<&Task> {
+        self.tasks.iter().find(|t| &t.id == uuid)
+    }
+    pub(crate) fn get_mut(&mut self, uuid: &Uuid) -> Option<&mut Task> {
+        self.tasks.iter_mut().find(|t| &t.id == uuid)
+    }
+    // the task is indexed by its external reference if it has one.
+    pub(crate) fn insert(&mut self, task: Task) {
+        if let Some(external_ref) = &task.external_ref {
+            self.refs
+                .insert((task.scope.clone(), external_ref.clone()), task.id);
+        }
+        self.tasks.push(task);
+    }
+    pub(crate) fn remove(&mut self, uuid: &Uuid) -> Option<Task> {
+        let index = self.tasks.iter().position(|t| &t.id == uuid)?;
+        let task = self.tasks.remove(index);
+        self.expiry.cancel(uuid);
+        self.audit.remove(uuid);
+        if let Some(external_ref) = &task.external_ref {
+            self.refs
+                .remove(&(task.scope.clone(), external_ref.clone()));
+        }
+        Some(task)
+    }
+    // schedule the finished task to be forgotten, its duration after it finished.
+    // a deadline already scheduled for this task is replaced, so it can be used to reschedule.
+    // pinned tasks are not scheduled, unfinished tasks keep the deadline of their maximum lifetime.
+    pub(crate) fn schedule_forget(&mut self, uuid: Uuid) {
+        let Some(task) = self.get(&uuid) else {
+            return;
+        };
+        let Some(finished_at) = task.finished_at else {
+            return;
+        };
+        if task.pinned {
+            self.expiry.cancel(&uuid);
+            return;
+        }
+        let deadline = finished_at.saturating_add(task.duration.into());
+        self.expiry.schedule_at(uuid, deadline);
+    }
+    // schedule the abortion of an unfinished task at the end of its maximum lifetime.
+    pub(crate) fn schedule_lifetime(&mut self, uuid: Uuid) {
+        if let Some(expires_at) = self.get(&uuid).and_then(|t| t.expires_at) {
+            self.expiry.schedule_at(uuid, expires_at);
+        }
+    }
+    // called by the reaper with the tasks whose deadline is passed.
+    // finished tasks are forgotten together in a single pass over the tasks, unfinished tasks reached their maximum lifetime and are aborted.
+    pub(crate) fn expire(&mut self, uuids: Vec<Uuid>) {
+        if uuids.is_empty() {
+            return;
+        }
+        let expired: HashSet<Uuid> = uuids.into_iter().collect();
+        let mut unfinished = Vec::new();
+        let (refs, expiry, audit) = (&mut self.refs, &mut self.expiry, &mut self.audit);
+        self.tasks.retain(|task| {
+            if !expired.contains(&task.id) {
+                return true;
+            }
+            if task.status != TaskStatus::Done && task.status != TaskStatus::Aborted {
+                unfinished.push(task.id);
+                return true;
+            }
+            expiry.cancel(&task.id);
+            audit.remove(&task.id);
+            if let Some(external_ref) = &task.external_ref {
+                refs.remove(&(task.scope.clone(), external_ref.clone()));
+            }
+            METRICS.expired("retention");
+            false
+        });
+        for uuid in unfinished {
+            self.abort_expired(uuid);
+        }
+    }
+    // abort an unfinished task at the end of its maximum lifetime.
+    fn abort_expired(&mut self, uuid: Uuid) {
+        let Some(task) = self.get_mut(&uuid) else {
+            return;
+        };
+        let now = unix_timestamp();
+        let old_status = task.status.as_str();
+        task.status = TaskStatus::Aborted;
+        task.description_result = format!(
+            "aborted by the server: maximum lifetime of {} seconds exceeded",
+            task.expires_at
+                .unwrap_or(now)
+                .saturating_sub(task.created_at)
+        );
+        task.touch(now);
+        task.finished_at = Some(now);
+        push_notification(task);
+        METRICS.expired("lifetime");
+        METRICS.finished(&task.status);
+        self.audit.record(
+            uuid,
+            "status",
+            old_status.to_string(),
+            "aborted (maximum lifetime)".to_string(),
+        );
+        // the aborted task is then retained for its normal duration.
+        self.schedule_forget(uuid);
+        dependencies::resolve_dependents(self, uuid);
+    }
+    // find a task by the external reference given at creation, unique in a scope.
+    pub(crate) fn by_ref(&self, scope: &str, external_ref: &str) -> Option<&Task> {
+        self.refs
+            .get(&(scope.to_string(), external_ref.to_string()))
+            .and_then(|uuid| self.get(uuid))
+    }
+}
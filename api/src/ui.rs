@@ -0,0 +1,30 @@
+use axum::{
+    extract::Path,
+    http::header::{CONTENT_SECURITY_POLICY, REFERRER_POLICY},
+    response::{Html, IntoResponse},
+};
+use uuid::Uuid;
+
+/// read-only web dashboard, the pages are embedded in the binary.
+// the pages are public, they get the tasks in json from the api with the token given by the user.
+const DASHBOARD: &str = include_str!("../assets/dashboard.html");
+const TASK_PAGE: &str = include_str!("../assets/task.html");
+
+// the pages only talk to the api, and the token in the url of a task page must not leak through the referrer.
+const HEADERS: [(axum::http::HeaderName, &str); 2] = [
+    (
+        CONTENT_SECURITY_POLICY,
+        "default-src 'none'; script-src 'unsafe-inline'; style-src 'unsafe-inline'; connect-src 'self'",
+    ),
+    (REFERRER_POLICY, "no-referrer"),
+];
+
+// list of all the tasks with filters, with the admin token.
+pub(crate) async fn dashboard() -> impl IntoResponse {
+    (HEADERS, Html(DASHBOARD))
+}
+
+// live progress of a task, the view token is given in the query as ?token=.
+pub(crate) async fn task_page(Path(_uuid): Path<Uuid>) -> impl IntoResponse {
+    (HEADERS, Html(TASK_PAGE))
+}
@@ -0,0 +1,384 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    error::Error,
+    io::Write,
+    path::{Path, PathBuf},
+    process::ExitCode,
+    time::Duration,
+};
+
+use clap::{Parser, Subcommand, ValueEnum};
+use output::Output;
+use serde::Deserialize;
+use tasks_tracker_client::{read_secret, Client, NewTask, TaskStatus};
+use url::Url;
+use uuid::Uuid;
+
+mod output;
+
+/// manage the tasks of a tasks-tracker server.
+///
+/// the url, token and output can also be set in a toml config file with the keys url, token, token-file and output.
+#[derive(Parser)]
+#[command(name = "tasks-tracker-cli")]
+struct Cli {
+    /// url of the tasks endpoint of the server, like http://127.0.0.1:8000/tasks
+    #[arg(long, env = "TASKS_TRACKER_URL", global = true)]
+    url: Option<Url>,
+    /// token of the request, the creation, admin, view, update or abort token depending on the command
+    #[arg(
+        long,
+        env = "TASKS_TRACKER_TOKEN",
+        hide_env_values = true,
+        global = true
+    )]
+    token: Option<String>,
+    /// file containing the token
+    #[arg(
+        long,
+        env = "TASKS_TRACKER_TOKEN_FILE",
+        global = true,
+        conflicts_with = "token"
+    )]
+    token_file: Option<PathBuf>,
+    /// config file, ~/.config/tasks-tracker/cli.toml is read if it exists
+    #[arg(long, env = "TASKS_TRACKER_CLI_CONFIG", global = true)]
+    config: Option<PathBuf>,
+    #[arg(long, short, value_enum, env = "TASKS_TRACKER_OUTPUT", global = true)]
+    output: Option<Output>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+struct Config {
+    url: Option<Url>,
+    token: Option<String>,
+    token_file: Option<PathBuf>,
+    output: Option<Output>,
+}
+
+// a task is given by its id or by its url.
+#[derive(Subcommand)]
+enum Command {
+    /// create a task with the creation token, prints its location and tokens
+    Create {
+        #[arg(long)]
+        scope: String,
+        #[arg(long)]
+        name: String,
+        #[arg(long, default_value = "")]
+        description: String,
+        /// seconds the task is kept after it finished
+        #[arg(long, default_value_t = 3600)]
+        duration: u32,
+        /// url receiving the push notifications, can be repeated
+        #[arg(long = "push")]
+        push_address: Vec<Url>,
+        /// label as key=value, can be repeated
+        #[arg(long = "label", value_parser = parse_label)]
+        labels: Vec<(String, String)>,
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        /// name of a step of the task, in order, can be repeated
+        #[arg(long = "step")]
+        steps: Vec<String>,
+        /// id of a task that must be done before this one starts, can be repeated
+        #[arg(long = "depends-on")]
+        dependencies: Vec<Uuid>,
+        /// reference of the task, unique in its scope
+        #[arg(long = "ref")]
+        external_ref: Option<String>,
+        /// seconds after which the task is aborted if it is not finished
+        #[arg(long)]
+        max_lifetime: Option<u32>,
+        /// retrying with the same key returns the task already created
+        #[arg(long)]
+        idempotency_key: Option<String>,
+    },
+    /// show a task with its view token
+    View { task: String },
+    /// show the progress of a task until it is finished, with its view token
+    ///
+    /// exits with 0 if the task is done and 2 if it is aborted.
+    Watch {
+        task: String,
+        /// seconds between two refreshes
+        #[arg(long, default_value_t = 1)]
+        interval: u64,
+    },
+    /// report the progress in % of a task, with its update token
+    Update {
+        task: String,
+        progress: u8,
+        /// index of the running step, the progress is then the one of the step
+        #[arg(long)]
+        step: Option<u32>,
+    },
+    /// mark a task as done, with its update token
+    Finish {
+        task: String,
+        #[arg(long)]
+        description: Option<String>,
+        /// file whose content is the payload of the result
+        #[arg(long)]
+        payload_file: Option<PathBuf>,
+    },
+    /// abort a task, with its abort token, or its update token with --update-token
+    Abort {
+        task: String,
+        #[arg(long)]
+        description: Option<String>,
+        /// the token is the update token of the task instead of its abort token
+        #[arg(long)]
+        update_token: bool,
+    },
+    /// list the tasks, with the admin token
+    List {
+        /// label selector, like env=prod,team!=billing
+        #[arg(long)]
+        selector: Option<String>,
+        /// tag the tasks must have, can be repeated
+        #[arg(long = "tag")]
+        tags: Vec<String>,
+        #[arg(long)]
+        scope: Option<String>,
+        #[arg(long, value_enum)]
+        status: Option<Status>,
+    },
+    /// forget a finished task immediately, with its update token
+    Purge { task: String },
+}
+
+#[derive(Clone, ValueEnum)]
+enum Status {
+    Active,
+    Aborted,
+    Done,
+    Blocked,
+}
+
+impl Status {
+    fn matches(&self, status: &TaskStatus) -> bool {
+        matches!(
+            (self, status),
+            (Status::Active, TaskStatus::Active)
+                | (Status::Aborted, TaskStatus::Aborted)
+                | (Status::Done, TaskStatus::Done)
+                | (Status::Blocked, TaskStatus::Blocked)
+        )
+    }
+}
+
+fn parse_label(label: &str) -> Result<(String, String), String> {
+    label
+        .split_once('=')
+        .map(|(key, value)| (key.to_string(), value.to_string()))
+        .ok_or_else(|| format!("label {} is not key=value", label))
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+    match run(cli).await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<ExitCode, Box<dyn Error>> {
+    let config = read_config(cli.config.as_ref())?;
+    // the command line and the environment take precedence over the config file.
+    let url = cli.url.or(config.url).ok_or(
+        "the url of the server is required, with --url, TASKS_TRACKER_URL or the config file",
+    )?;
+    let token = match (cli.token, cli.token_file) {
+        (Some(token), _) => Some(token),
+        (None, Some(path)) => Some(read_token(&path)?),
+        (None, None) => match (config.token, config.token_file) {
+            (Some(token), _) => Some(token),
+            (None, Some(path)) => Some(read_token(&path)?),
+            (None, None) => None,
+        },
+    };
+    let token = token.as_deref();
+    let output = cli.output.or(config.output).unwrap_or(Output::Table);
+    let client = Client::new(url.clone())?;
+    match cli.command {
+        Command::Create {
+            scope,
+            name,
+            description,
+            duration,
+            push_address,
+            labels,
+            tags,
+            steps,
+            dependencies,
+            external_ref,
+            max_lifetime,
+            idempotency_key,
+        } => {
+            let new_task = NewTask {
+                duration,
+                scope,
+                name,
+                description,
+                push_address,
+                payload: Vec::new(),
+                dependencies,
+                steps,
+                labels: labels.into_iter().collect::<BTreeMap<_, _>>(),
+                tags: tags.into_iter().collect::<BTreeSet<_>>(),
+                external_ref,
+                max_lifetime,
+            };
+            let created = client
+                .create_task_with_key(new_task, idempotency_key.as_deref(), token)
+                .await?;
+            output::created(output, &created);
+        }
+        Command::View { task } => {
+            let task = client.get_task(&task_url(&url, &task)?, token).await?;
+            output::task(output, &task);
+        }
+        Command::Watch { task, interval } => {
+            let location = task_url(&url, &task)?;
+            loop {
+                let task = client.get_task(&location, token).await?;
+                output::progress(output, &task);
+                match task.status {
+                    TaskStatus::Done => return Ok(ExitCode::SUCCESS),
+                    TaskStatus::Aborted => return Ok(ExitCode::from(2)),
+                    _ => {}
+                }
+                std::io::stdout().flush()?;
+                tokio::time::sleep(Duration::from_secs(interval)).await;
+            }
+        }
+        Command::Update {
+            task,
+            progress,
+            step,
+        } => {
+            let location = task_url(&url, &task)?;
+            match step {
+                Some(index) => {
+                    client
+                        .update_task_step(&location, index, progress, token)
+                        .await?
+                }
+                None => {
+                    client
+                        .update_task_progress(&location, progress, token)
+                        .await?
+                }
+            }
+        }
+        Command::Finish {
+            task,
+            description,
+            payload_file,
+        } => {
+            let payload = match payload_file {
+                Some(path) => std::fs::read(path)?,
+                None => Vec::new(),
+            };
+            client
+                .finish_task(
+                    &task_url(&url, &task)?,
+                    description.as_deref(),
+                    &payload,
+                    token,
+                )
+                .await?;
+        }
+        Command::Abort {
+            task,
+            description,
+            update_token,
+        } => {
+            let location = task_url(&url, &task)?;
+            // DELETE only accepts the abort token, the update token aborts the task with PATCH.
+            if update_token {
+                client
+                    .abort_task_with_update_token(&location, description.as_deref(), &[], token)
+                    .await?
+            } else {
+                client
+                    .abort_task(&location, description.as_deref(), &[], token)
+                    .await?
+            }
+        }
+        Command::List {
+            selector,
+            tags,
+            scope,
+            status,
+        } => {
+            let tags = tags.iter().map(String::as_str).collect::<Vec<_>>();
+            let mut tasks = client.list_tasks(selector.as_deref(), &tags, token).await?;
+            tasks.retain(|task| {
+                scope.as_ref().is_none_or(|scope| &task.scope == scope)
+                    && status
+                        .as_ref()
+                        .is_none_or(|status| status.matches(&task.status))
+            });
+            output::tasks(output, &tasks);
+        }
+        Command::Purge { task } => {
+            client.purge_task(&task_url(&url, &task)?, token).await?;
+        }
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+// the config file given must exist, the default one is optional.
+fn read_config(path: Option<&PathBuf>) -> Result<Config, Box<dyn Error>> {
+    let (path, required) = match path {
+        Some(path) => (path.clone(), true),
+        None => match default_config_path() {
+            Some(path) => (path, false),
+            None => return Ok(Config::default()),
+        },
+    };
+    match std::fs::read_to_string(&path) {
+        Ok(content) => Ok(toml::from_str(&content)
+            .map_err(|e| format!("invalid config file {}: {}", path.display(), e))?),
+        Err(e) if required || e.kind() != std::io::ErrorKind::NotFound => {
+            Err(format!("can not read the config file {}: {}", path.display(), e).into())
+        }
+        Err(_) => Ok(Config::default()),
+    }
+}
+
+fn default_config_path() -> Option<PathBuf> {
+    let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?;
+    Some(config_dir.join("tasks-tracker").join("cli.toml"))
+}
+
+fn read_token(path: &Path) -> Result<String, Box<dyn Error>> {
+    Ok(read_secret(path)
+        .map_err(|e| format!("can not read the token file {}: {}", path.display(), e))?)
+}
+
+// url of a task given by its id, or its url as printed at creation.
+fn task_url(url: &Url, task: &str) -> Result<Url, Box<dyn Error>> {
+    if let Ok(location) = Url::parse(task) {
+        return Ok(location);
+    }
+    let id = Uuid::parse_str(task).map_err(|_| format!("{} is not a task id or url", task))?;
+    let mut location = url.clone();
+    location
+        .path_segments_mut()
+        .map_err(|_| "the url of the server can not have a path")?
+        .pop_if_empty()
+        .push(&id.to_string());
+    Ok(location)
+}
@@ -0,0 +1,172 @@
+use clap::ValueEnum;
+use serde::Deserialize;
+use serde_json::json;
+use tasks_tracker_client::{ResponseNewTask, Task, TaskStatus};
+
+// width of the progress bars in characters.
+const BAR_WIDTH: usize = 30;
+
+/// format of what the commands print.
+#[derive(Clone, Copy, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Output {
+    Table,
+    Json,
+}
+
+pub(crate) fn created(output: Output, created: &ResponseNewTask) {
+    match output {
+        Output::Table => print_table(&[
+            vec!["location".to_string(), created.location.to_string()],
+            vec!["view token".to_string(), created.view_token.clone()],
+            vec!["update token".to_string(), created.update_token.clone()],
+            vec!["abort token".to_string(), created.abort_token.clone()],
+        ]),
+        Output::Json => println!(
+            "{}",
+            json!({
+                "location": created.location,
+                "view_token": created.view_token,
+                "update_token": created.update_token,
+                "abort_token": created.abort_token,
+            })
+        ),
+    }
+}
+
+pub(crate) fn task(output: Output, task: &Task) {
+    if let Output::Json = output {
+        println!("{}", serde_json::to_string_pretty(task).unwrap());
+        return;
+    }
+    let mut rows = vec![
+        vec!["id".to_string(), task.id.to_string()],
+        vec!["scope".to_string(), task.scope.clone()],
+        vec!["name".to_string(), task.name.clone()],
+        vec!["status".to_string(), task.status.as_str().to_string()],
+        vec!["progress".to_string(), progress_bar(task.progress)],
+    ];
+    if let Some(count) = &task.progress_count {
+        rows.push(vec![
+            "count".to_string(),
+            format!("{}/{} {}", count.current, count.total, count.unit),
+        ]);
+    }
+    for (index, step) in task.steps.iter().enumerate() {
+        rows.push(vec![
+            format!("step {}", index),
+            format!("{} {}%", step.name, step.progress),
+        ]);
+    }
+    if !task.description.is_empty() {
+        rows.push(vec!["description".to_string(), task.description.clone()]);
+    }
+    if !task.labels.is_empty() {
+        let labels = task
+            .labels
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<_>>();
+        rows.push(vec!["labels".to_string(), labels.join(",")]);
+    }
+    if !task.tags.is_empty() {
+        let tags = task.tags.iter().cloned().collect::<Vec<_>>();
+        rows.push(vec!["tags".to_string(), tags.join(",")]);
+    }
+    rows.push(vec!["created at".to_string(), task.created_at.to_string()]);
+    if let Some(estimated) = task.estimated_completion {
+        rows.push(vec![
+            "estimated completion".to_string(),
+            estimated.to_string(),
+        ]);
+    }
+    if let Some(finished_at) = task.finished_at {
+        rows.push(vec!["finished at".to_string(), finished_at.to_string()]);
+        rows.push(vec!["result".to_string(), task.description_result.clone()]);
+    }
+    print_table(&rows);
+}
+
+// the progress bar is redrawn on the same line, json prints a line for each refresh.
+pub(crate) fn progress(output: Output, task: &Task) {
+    let finished = matches!(task.status, TaskStatus::Done | TaskStatus::Aborted);
+    match output {
+        Output::Table => {
+            let step = task
+                .current_step
+                .and_then(|index| task.steps.get(index as usize))
+                .map(|step| format!(" {}", step.name))
+                .unwrap_or_default();
+            print!(
+                "\r\x1b[2K{} {}{}",
+                progress_bar(task.progress),
+                task.status.as_str(),
+                step
+            );
+            if finished {
+                println!();
+            }
+        }
+        Output::Json => println!(
+            "{}",
+            json!({
+                "status": task.status,
+                "progress": task.progress,
+                "current_step": task.current_step,
+                "updated_at": task.updated_at,
+            })
+        ),
+    }
+}
+
+pub(crate) fn tasks(output: Output, tasks: &[Task]) {
+    if let Output::Json = output {
+        println!("{}", serde_json::to_string_pretty(tasks).unwrap());
+        return;
+    }
+    let mut rows = vec![["ID", "SCOPE", "NAME", "STATUS", "PROGRESS"]
+        .map(str::to_string)
+        .to_vec()];
+    for task in tasks {
+        rows.push(vec![
+            task.id.to_string(),
+            task.scope.clone(),
+            task.name.clone(),
+            task.status.as_str().to_string(),
+            format!("{}%", task.progress),
+        ]);
+    }
+    print_table(&rows);
+}
+
+fn progress_bar(progress: u8) -> String {
+    let filled = BAR_WIDTH * usize::from(progress.min(100)) / 100;
+    format!(
+        "[{}{}] {:>3}%",
+        "#".repeat(filled),
+        "-".repeat(BAR_WIDTH - filled),
+        progress
+    )
+}
+
+// print the rows with their columns aligned.
+fn print_table(rows: &[Vec<String>]) {
+    let columns = rows.iter().map(Vec::len).max().unwrap_or_default();
+    let widths = (0..columns)
+        .map(|column| {
+            rows.iter()
+                .filter_map(|row| row.get(column))
+                .map(|cell| cell.chars().count())
+                .max()
+                .unwrap_or_default()
+        })
+        .collect::<Vec<_>>();
+    for row in rows {
+        let line = row
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect::<Vec<_>>();
+        println!("{}", line.join("  ").trim_end());
+    }
+}
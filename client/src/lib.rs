@@ -3,11 +3,16 @@ use error::TaskClientError;
 use reqwest::header::InvalidHeaderValue;
 use reqwest::{header, RequestBuilder};
 use reqwest::{
-    header::{HeaderValue, AUTHORIZATION, CONTENT_LOCATION},
+    header::{HeaderValue, AUTHORIZATION, CONTENT_LOCATION, IF_MATCH},
     Client as ReqClient, ClientBuilder, Response,
 };
+use std::collections::{BTreeMap, BTreeSet};
 /// re-export for client app
-pub use tasks_tracker_common::{NewTask, Task, TaskStatus, BINCODE_CONFIG};
+pub use tasks_tracker_common::{
+    read_secret, AbortFilter, AbortResult, AuditEntry, BatchOperation, BatchResult, LogEntry,
+    LogLevel, NewTask, Notification, ProgressCount, ScopeStats, Stats, Step, StepStatus, Task,
+    TaskStatus, TaskUpdate, BINCODE_CONFIG,
+};
 use url::Url;
 pub mod error;
 pub struct ResponseNewTask {
@@ -46,15 +51,27 @@ impl Client {
         &self,
         new_task: NewTask,
         token: Option<&str>,
+    ) -> Result<ResponseNewTask, TaskClientError> {
+        self.create_task_with_key(new_task, None, token).await
+    }
+    /// create a task with an idempotency key.
+    /// retrying the creation with the same key and token returns the task already created instead of a new one.
+    pub async fn create_task_with_key(
+        &self,
+        new_task: NewTask,
+        idempotency_key: Option<&str>,
+        token: Option<&str>,
     ) -> Result<ResponseNewTask, TaskClientError> {
         let body = bincode::encode_to_vec(new_task, BINCODE_CONFIG)?;
-        let rep = request_with_token(self.post(self.default_url.as_str()), token)?
-            .body(body)
-            .send()
-            .await?
-            .error_for_status()?;
+        let mut req = request_with_token(self.post(self.default_url.as_str()), token)?;
+        if let Some(key) = idempotency_key {
+            req = req.header("Idempotency-Key", HeaderValue::from_str(key)?);
+        }
+        let rep = req.body(body).send().await?.error_for_status()?;
         Ok(ResponseNewTask {
-            location: rep_header_string(&rep, CONTENT_LOCATION.as_str())?.parse()?,
+            location: self
+                .default_url
+                .join(&rep_header_string(&rep, CONTENT_LOCATION.as_str())?)?,
             view_token: rep_header_string(&rep, "ViewToken")?,
             abort_token: rep_header_string(&rep, "AbortToken")?,
             update_token: rep_header_string(&rep, "UpdateToken")?,
@@ -74,6 +91,12 @@ impl Client {
                 description: String::new(),
                 push_address: Vec::new(),
                 payload: Vec::new(),
+                dependencies: Vec::new(),
+                steps: Vec::new(),
+                labels: BTreeMap::new(),
+                tags: BTreeSet::new(),
+                external_ref: None,
+                max_lifetime: None,
             },
             BINCODE_CONFIG,
         )?;
@@ -83,7 +106,9 @@ impl Client {
             .await?
             .error_for_status()?;
         Ok(ResponseNewTask {
-            location: rep_header_string(&rep, CONTENT_LOCATION.as_str())?.parse()?,
+            location: self
+                .default_url
+                .join(&rep_header_string(&rep, CONTENT_LOCATION.as_str())?)?,
             view_token: rep_header_string(&rep, "ViewToken")?,
             abort_token: rep_header_string(&rep, "AbortToken")?,
             update_token: rep_header_string(&rep, "UpdateToken")?,
@@ -95,12 +120,60 @@ impl Client {
         new_progress: u8,
         token: Option<&str>,
     ) -> Result<(), TaskClientError> {
-        let body = bincode::encode_to_vec((new_progress, TaskStatus::Active), BINCODE_CONFIG)?;
-        request_with_token(self.post(task_location.as_str()), token)?
-            .body(body)
-            .send()
-            .await?
-            .error_for_status()?;
+        self.update_task(task_location, TaskUpdate::progress(new_progress), token)
+            .await
+    }
+    /// report the progress as a count of units, the percentage is derived by the server.
+    pub async fn update_task_progress_count(
+        &self,
+        task_location: &Url,
+        current: u64,
+        total: u64,
+        unit: &str,
+        token: Option<&str>,
+    ) -> Result<(), TaskClientError> {
+        self.update_task(
+            task_location,
+            TaskUpdate::progress_count(current, total, unit.to_string()),
+            token,
+        )
+        .await
+    }
+    /// mark the step at index as running, with its own progress in %.
+    pub async fn update_task_step(
+        &self,
+        task_location: &Url,
+        index: u32,
+        step_progress: u8,
+        token: Option<&str>,
+    ) -> Result<(), TaskClientError> {
+        self.update_task(task_location, TaskUpdate::step(index, step_progress), token)
+            .await
+    }
+    pub async fn update_task(
+        &self,
+        task_location: &Url,
+        update: TaskUpdate,
+        token: Option<&str>,
+    ) -> Result<(), TaskClientError> {
+        self.update_task_if_match(task_location, update, None, token)
+            .await
+    }
+    /// update the task only if its revision is still the one of the given ETag (see `Task::etag`).
+    /// the server responds with 412 Precondition Failed if the task was modified in the meantime.
+    pub async fn update_task_if_match(
+        &self,
+        task_location: &Url,
+        update: TaskUpdate,
+        etag: Option<&str>,
+        token: Option<&str>,
+    ) -> Result<(), TaskClientError> {
+        let body = bincode::encode_to_vec(update, BINCODE_CONFIG)?;
+        let mut req = request_with_token(self.patch(task_location.as_str()), token)?;
+        if let Some(etag) = etag {
+            req = req.header(IF_MATCH, HeaderValue::from_str(etag)?);
+        }
+        req.body(body).send().await?.error_for_status()?;
         Ok(())
     }
     pub async fn finish_task(
@@ -110,17 +183,19 @@ impl Client {
         payload_result: &[u8],
         token: Option<&str>,
     ) -> Result<(), TaskClientError> {
-        let body = bincode::encode_to_vec(
-            (100u8, TaskStatus::Done, description_result, payload_result),
-            BINCODE_CONFIG,
-        )?;
-        request_with_token(self.post(task_location.as_str()), token)?
-            .body(body)
-            .send()
-            .await?
-            .error_for_status()?;
-        Ok::<(), TaskClientError>(())
+        self.update_task(
+            task_location,
+            TaskUpdate::finish(
+                TaskStatus::Done,
+                description_result.map(str::to_string),
+                payload_result.to_vec(),
+            ),
+            token,
+        )
+        .await
     }
+    /// abort the task with DELETE, the token must be the abort token or the admin token.
+    /// the server responds with 403 Forbidden to the update token, use `abort_task_with_update_token` instead.
     pub async fn abort_task(
         &self,
         task_location: &Url,
@@ -129,16 +204,74 @@ impl Client {
         token: Option<&str>,
     ) -> Result<(), TaskClientError> {
         let body = bincode::encode_to_vec(
-            (0u8, TaskStatus::Aborted, description_result, payload_result),
+            TaskUpdate::finish(
+                TaskStatus::Aborted,
+                description_result.map(str::to_string),
+                payload_result.to_vec(),
+            ),
             BINCODE_CONFIG,
         )?;
-        request_with_token(self.post(task_location.as_str()), token)?
+        request_with_token(self.delete(task_location.as_str()), token)?
             .body(body)
             .send()
             .await?
             .error_for_status()?;
         Ok::<(), TaskClientError>(())
     }
+    /// abort the task with PATCH, the token must be the update token or the admin token.
+    pub async fn abort_task_with_update_token(
+        &self,
+        task_location: &Url,
+        description_result: Option<&str>,
+        payload_result: &[u8],
+        token: Option<&str>,
+    ) -> Result<(), TaskClientError> {
+        self.update_task(
+            task_location,
+            TaskUpdate::finish(
+                TaskStatus::Aborted,
+                description_result.map(str::to_string),
+                payload_result.to_vec(),
+            ),
+            token,
+        )
+        .await
+    }
+    /// change how long the task is kept after it finished, with the update token.
+    /// pinning the task so it is never forgotten requires the admin token.
+    pub async fn set_task_retention(
+        &self,
+        task_location: &Url,
+        duration: Option<u32>,
+        pinned: Option<bool>,
+        token: Option<&str>,
+    ) -> Result<(), TaskClientError> {
+        let body = bincode::encode_to_vec((duration, pinned), BINCODE_CONFIG)?;
+        request_with_token(
+            self.put(sub_resource(task_location, "retention").as_str()),
+            token,
+        )?
+        .body(body)
+        .send()
+        .await?
+        .error_for_status()?;
+        Ok(())
+    }
+    /// forget a finished task immediately, with the update token.
+    pub async fn purge_task(
+        &self,
+        task_location: &Url,
+        token: Option<&str>,
+    ) -> Result<(), TaskClientError> {
+        request_with_token(
+            self.post(sub_resource(task_location, "purge").as_str()),
+            token,
+        )?
+        .send()
+        .await?
+        .error_for_status()?;
+        Ok(())
+    }
     pub async fn get_task(
         &self,
         task_location: &Url,
@@ -155,6 +288,196 @@ impl Client {
         )?
         .0)
     }
+    /// apply a list of create, update and abort operations in one request.
+    /// each operation carries its own token, the results are in the same order as the operations.
+    pub async fn batch(
+        &self,
+        operations: Vec<BatchOperation>,
+    ) -> Result<Vec<BatchResult>, TaskClientError> {
+        let body = bincode::encode_to_vec(operations, BINCODE_CONFIG)?;
+        Ok(bincode::decode_from_slice(
+            &self
+                .post(sub_resource(&self.default_url, "batch").as_str())
+                .body(body)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?,
+            BINCODE_CONFIG,
+        )?
+        .0)
+    }
+    /// abort all the unfinished tasks matching the filter, with the admin token.
+    /// returns the ids of the matching tasks, which are not aborted if the filter is a dry run.
+    pub async fn abort_tasks(
+        &self,
+        filter: AbortFilter,
+        token: Option<&str>,
+    ) -> Result<AbortResult, TaskClientError> {
+        let body = bincode::encode_to_vec(filter, BINCODE_CONFIG)?;
+        Ok(bincode::decode_from_slice(
+            &request_with_token(self.post(self.api_url(&["admin", "abort"]).as_str()), token)?
+                .body(body)
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?,
+            BINCODE_CONFIG,
+        )?
+        .0)
+    }
+    /// aggregated figures of the server, with the admin token.
+    pub async fn stats(&self, token: Option<&str>) -> Result<Stats, TaskClientError> {
+        Ok(bincode::decode_from_slice(
+            &request_with_token(self.get(self.api_url(&["stats"]).as_str()), token)?
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?,
+            BINCODE_CONFIG,
+        )?
+        .0)
+    }
+    // url of an endpoint of the api, relative to the parent of the tasks url.
+    fn api_url(&self, path: &[&str]) -> Url {
+        let mut url = self.default_url.clone();
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments.pop_if_empty().pop().extend(path);
+        }
+        url
+    }
+    /// get a task from the external reference given at creation in its scope.
+    pub async fn get_task_by_ref(
+        &self,
+        scope: &str,
+        external_ref: &str,
+        token: Option<&str>,
+    ) -> Result<Task, TaskClientError> {
+        let mut url = self.default_url.clone();
+        if let Ok(mut segments) = url.path_segments_mut() {
+            segments
+                .pop_if_empty()
+                .extend(["by-ref", scope, external_ref]);
+        }
+        self.get_task(&url, token).await
+    }
+    /// replace the labels and tags of the task, with the update token.
+    pub async fn set_task_labels(
+        &self,
+        task_location: &Url,
+        labels: BTreeMap<String, String>,
+        tags: BTreeSet<String>,
+        token: Option<&str>,
+    ) -> Result<(), TaskClientError> {
+        let body = bincode::encode_to_vec((labels, tags), BINCODE_CONFIG)?;
+        request_with_token(
+            self.put(sub_resource(task_location, "labels").as_str()),
+            token,
+        )?
+        .body(body)
+        .send()
+        .await?
+        .error_for_status()?;
+        Ok(())
+    }
+    /// list all tasks matching a label selector (`env=prod,team!=billing`) and a set of tags, with the admin token.
+    pub async fn list_tasks(
+        &self,
+        selector: Option<&str>,
+        tags: &[&str],
+        token: Option<&str>,
+    ) -> Result<Vec<Task>, TaskClientError> {
+        let mut url = self.default_url.clone();
+        if let Some(selector) = selector {
+            url.query_pairs_mut().append_pair("labels", selector);
+        }
+        if !tags.is_empty() {
+            url.query_pairs_mut().append_pair("tags", &tags.join(","));
+        }
+        Ok(bincode::decode_from_slice(
+            &request_with_token(self.get(url.as_str()), token)?
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?,
+            BINCODE_CONFIG,
+        )?
+        .0)
+    }
+    /// append an entry to the log of the task, with the update token.
+    pub async fn append_log(
+        &self,
+        task_location: &Url,
+        level: LogLevel,
+        message: &str,
+        token: Option<&str>,
+    ) -> Result<(), TaskClientError> {
+        let body = bincode::encode_to_vec((level, message), BINCODE_CONFIG)?;
+        request_with_token(
+            self.post(sub_resource(task_location, "log").as_str()),
+            token,
+        )?
+        .body(body)
+        .send()
+        .await?
+        .error_for_status()?;
+        Ok(())
+    }
+    /// read the log entries of the task starting at the sequence number `from`, with the view token.
+    pub async fn get_log(
+        &self,
+        task_location: &Url,
+        from: u64,
+        limit: Option<usize>,
+        token: Option<&str>,
+    ) -> Result<Vec<LogEntry>, TaskClientError> {
+        let mut url = sub_resource(task_location, "log");
+        url.query_pairs_mut().append_pair("from", &from.to_string());
+        if let Some(limit) = limit {
+            url.query_pairs_mut()
+                .append_pair("limit", &limit.to_string());
+        }
+        Ok(bincode::decode_from_slice(
+            &request_with_token(self.get(url.as_str()), token)?
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?,
+            BINCODE_CONFIG,
+        )?
+        .0)
+    }
+    /// changes made to a task, readable with the update or admin token.
+    pub async fn get_task_audit(
+        &self,
+        task_location: &Url,
+        token: Option<&str>,
+    ) -> Result<Vec<AuditEntry>, TaskClientError> {
+        let url = sub_resource(task_location, "audit");
+        Ok(bincode::decode_from_slice(
+            &request_with_token(self.get(url.as_str()), token)?
+                .send()
+                .await?
+                .error_for_status()?
+                .bytes()
+                .await?,
+            BINCODE_CONFIG,
+        )?
+        .0)
+    }
+}
+// url of a resource nested under the task location.
+fn sub_resource(task_location: &Url, name: &str) -> Url {
+    let mut url = task_location.clone();
+    if let Ok(mut segments) = url.path_segments_mut() {
+        segments.pop_if_empty().push(name);
+    }
+    url
 }
 fn rep_header_string(rep: &Response, key: &str) -> Result<String, TaskClientError> {
     Ok(rep
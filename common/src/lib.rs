@@ -2,12 +2,35 @@ use bincode::{Decode, Encode};
 use rand::distributions::Alphanumeric;
 use rand::thread_rng;
 use rand::Rng;
+use serde::Serialize;
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
 use url::Url;
 use uuid::Uuid;
 
 pub const BINCODE_CONFIG: bincode::config::Configuration = bincode::config::standard();
+// maximum number of progress samples kept for a task, older samples are discarded.
+pub const PROGRESS_HISTORY_LEN: usize = 64;
+
+// maximum number of log entries kept for a task, older entries are discarded.
+pub const LOG_LEN: usize = 128;
+
+// current time in seconds since the unix epoch, used for every timestamp of a task.
+pub fn unix_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+// read a token from a file, secrets files often end with a newline which is not part of the token.
+pub fn read_secret(path: &Path) -> std::io::Result<String> {
+    Ok(std::fs::read_to_string(path)?.trim_end().to_string())
+}
 // Possible status variant of a task.
-#[derive(Clone, Default, PartialEq, Encode, Decode)]
+#[derive(Clone, Default, PartialEq, Encode, Decode, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum TaskStatus {
     // the task has been started and is currently progressing.
     #[default]
@@ -16,9 +39,83 @@ pub enum TaskStatus {
     Aborted,
     // the task finished successfully.
     Done,
+    // the task is waiting for its dependencies to be done before it can start.
+    Blocked,
+}
+
+impl TaskStatus {
+    // name of the status in lowercase.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TaskStatus::Active => "active",
+            TaskStatus::Aborted => "aborted",
+            TaskStatus::Done => "done",
+            TaskStatus::Blocked => "blocked",
+        }
+    }
+}
+
+// progress expressed as a count of units, for example 3412 of 10000 files.
+#[derive(Clone, PartialEq, Encode, Decode, Serialize)]
+pub struct ProgressCount {
+    pub current: u64,
+    pub total: u64,
+    // unit of the count, "bytes", "files"...
+    pub unit: String,
+}
+
+impl ProgressCount {
+    // percentage derived from the count, a total of zero is considered as no progress.
+    pub fn percentage(&self) -> u8 {
+        if self.total == 0 {
+            return 0;
+        }
+        (u128::from(self.current.min(self.total)) * 100 / u128::from(self.total)) as u8
+    }
+}
+
+// severity of a log entry.
+#[derive(Clone, Default, PartialEq, Encode, Decode, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogLevel {
+    Debug,
+    #[default]
+    Info,
+    Warning,
+    Error,
 }
 
-#[derive(Clone, Encode)]
+// entry of the log of a task, written by the producer to say what it is doing.
+#[derive(Clone, Encode, Decode, Serialize)]
+pub struct LogEntry {
+    // increasing number of the entry in the log, used to paginate.
+    pub sequence: u64,
+    pub timestamp: u64,
+    pub level: LogLevel,
+    pub message: String,
+}
+
+// status of a step of a task.
+#[derive(Clone, Default, PartialEq, Encode, Decode, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StepStatus {
+    #[default]
+    Pending,
+    Running,
+    Done,
+}
+
+// named phase of a task, declared at creation.
+#[derive(Clone, Encode, Decode, Serialize)]
+pub struct Step {
+    pub name: String,
+    pub status: StepStatus,
+    // progress in % of the step itself.
+    pub progress: u8,
+}
+
+// the json form of a task, used by the cli and the dashboard, never contains the tokens.
+#[derive(Clone, Encode, Serialize)]
 #[cfg_attr(feature = "client", derive(Decode))]
 pub struct Task {
     // identifier of the task that will be provided when created.
@@ -42,6 +139,7 @@ pub struct Task {
     // The first is to view progress and status.
     // Second one is only to change the status to abort.
     // Third one is to update the progress and status.
+    #[serde(skip)]
     pub tokens: (String, String, String),
     // Url where to send push notifications.
     // #[bitcode(with_serde)]
@@ -51,8 +149,124 @@ pub struct Task {
     pub description_result: String,
     // a payload can also be set for the result
     pub payload_result: Vec<u8>,
+    // ids of the tasks that must be done before this one can start.
+    #[bincode(with_serde)]
+    pub dependencies: Vec<Uuid>,
+    // timestamps in seconds since unix epoch.
+    pub created_at: u64,
+    // set when the task becomes active, can be later than creation if the task was blocked.
+    pub started_at: Option<u64>,
+    pub updated_at: u64,
+    // incremented on each modification of the task, used as its ETag.
+    pub revision: u64,
+    pub finished_at: Option<u64>,
+    // bounded history of (timestamp, progress) samples, oldest first.
+    pub progress_history: Vec<(u64, u8)>,
+    // estimated timestamp of completion, computed from the progress history when the task is viewed.
+    pub estimated_completion: Option<u64>,
+    // bounded append-only log of the task, oldest first.
+    pub log: Vec<LogEntry>,
+    // ordered steps of the task, can be empty.
+    pub steps: Vec<Step>,
+    // index of the running step.
+    pub current_step: Option<u32>,
+    // key-value labels to classify the task.
+    pub labels: BTreeMap<String, String>,
+    pub tags: BTreeSet<String>,
+    // reference of the task given by its creator, unique in the scope.
+    pub external_ref: Option<String>,
+    // a pinned task is never forgotten automatically, set by an admin.
+    pub pinned: bool,
+    // timestamp after which the task is aborted by the server if it is not finished.
+    pub expires_at: Option<u64>,
+    // optional progress in units, the progress in % is then derived from it.
+    // last field so clients decoding the previous Task still read the fields before it.
+    pub progress_count: Option<ProgressCount>,
 }
-#[derive(Decode)]
+
+impl Task {
+    // record a new progress value with the time at which it was given.
+    pub fn record_progress(&mut self, progress: u8, timestamp: u64) {
+        self.progress = progress;
+        self.touch(timestamp);
+        if self.progress_history.len() >= PROGRESS_HISTORY_LEN {
+            self.progress_history.remove(0);
+        }
+        self.progress_history.push((timestamp, progress));
+    }
+    // append an entry to the log, the oldest entry is discarded if the log is full.
+    pub fn append_log(&mut self, level: LogLevel, message: String, timestamp: u64) {
+        let sequence = self.log.last().map_or(0, |e| e.sequence + 1);
+        if self.log.len() >= LOG_LEN {
+            self.log.remove(0);
+        }
+        self.log.push(LogEntry {
+            sequence,
+            timestamp,
+            level,
+            message,
+        });
+        self.touch(timestamp);
+    }
+    // mark the task as modified at the given time.
+    pub fn touch(&mut self, timestamp: u64) {
+        self.updated_at = timestamp;
+        self.revision += 1;
+    }
+    // entity tag of the current revision of the task.
+    pub fn etag(&self) -> String {
+        format!("\"{}\"", self.revision)
+    }
+    // progress of the whole task derived from the running step and its own progress.
+    // returns None if the index is out of bounds.
+    pub fn steps_progress(&self, index: usize, progress: u8) -> Option<u8> {
+        if index >= self.steps.len() {
+            return None;
+        }
+        Some(((index * 100 + usize::from(progress.min(100))) / self.steps.len()) as u8)
+    }
+    // mark the step at index as running with its own progress, previous steps are done and next ones pending.
+    // an index out of bounds is ignored, it must be rejected with steps_progress first.
+    pub fn set_current_step(&mut self, index: usize, progress: u8) {
+        if index >= self.steps.len() {
+            return;
+        }
+        for (i, step) in self.steps.iter_mut().enumerate() {
+            (step.status, step.progress) = match i.cmp(&index) {
+                std::cmp::Ordering::Less => (StepStatus::Done, 100),
+                std::cmp::Ordering::Equal => (StepStatus::Running, progress.min(100)),
+                std::cmp::Ordering::Greater => (StepStatus::Pending, 0),
+            };
+        }
+        self.current_step = Some(index as u32);
+    }
+    // mark every step as done, when the task is done.
+    pub fn complete_steps(&mut self) {
+        for step in self.steps.iter_mut() {
+            step.status = StepStatus::Done;
+            step.progress = 100;
+        }
+        self.current_step = None;
+    }
+    // estimate the time of completion by extrapolating the average throughput of the progress history.
+    // returns None if the task is not active or if the progress did not advance yet.
+    pub fn estimate_completion(&self) -> Option<u64> {
+        if self.status != TaskStatus::Active {
+            return None;
+        }
+        let (first_time, first_progress) = *self.progress_history.first()?;
+        let (last_time, last_progress) = *self.progress_history.last()?;
+        if last_progress <= first_progress || last_time <= first_time {
+            return None;
+        }
+        let elapsed = (last_time - first_time) as f64;
+        let rate = f64::from(last_progress - first_progress) / elapsed;
+        let remaining = f64::from(100u8.saturating_sub(last_progress)) / rate;
+        Some(last_time + remaining.ceil() as u64)
+    }
+}
+// the default is a task without any optional setting, to fill with `..NewTask::default()`.
+#[derive(Default, Decode)]
 #[cfg_attr(feature = "client", derive(Encode))]
 pub struct NewTask {
     pub duration: u32,
@@ -62,11 +276,235 @@ pub struct NewTask {
     #[bincode(with_serde)]
     pub push_address: Vec<Url>,
     pub payload: Vec<u8>,
+    // ids of existing tasks that must be done before this one can start.
+    #[bincode(with_serde)]
+    pub dependencies: Vec<Uuid>,
+    // names of the ordered steps of the task.
+    pub steps: Vec<String>,
+    pub labels: BTreeMap<String, String>,
+    pub tags: BTreeSet<String>,
+    // natural identifier of the task for its creator (an order number...), unique in the scope.
+    // the task can then be retrieved at /tasks/by-ref/{scope}/{external_ref}.
+    pub external_ref: Option<String>,
+    // seconds after creation after which the task is aborted if it is not finished.
+    // the server can enforce a shorter maximum lifetime.
+    pub max_lifetime: Option<u32>,
+}
+
+// change of a task recorded by the server, readable with the update token.
+#[derive(Clone, Encode, Serialize)]
+#[cfg_attr(feature = "client", derive(Decode))]
+pub struct AuditEntry {
+    pub timestamp: u64,
+    // privilege used for the change: admin, creation, update, abort, or system for changes made by the server.
+    pub privilege: String,
+    pub client_ip: Option<String>,
+    // what was changed: create, status, progress, labels, retention.
+    pub action: String,
+    pub old_value: String,
+    pub new_value: String,
+}
+
+// body of the push notifications sent to the push addresses of a task.
+#[derive(Encode)]
+#[cfg_attr(feature = "client", derive(Decode))]
+pub struct Notification {
+    #[bincode(with_serde)]
+    pub id: Uuid,
+    pub status: TaskStatus,
+    pub progress: u8,
+    pub labels: BTreeMap<String, String>,
+    pub tags: BTreeSet<String>,
+    // entry appended to the log of the task, None if the notification is for a change of status.
+    // last field so receivers decoding the previous notification still read the fields before it.
+    pub log_entry: Option<LogEntry>,
+}
+
+impl From<&Task> for Notification {
+    fn from(task: &Task) -> Self {
+        Notification {
+            id: task.id,
+            status: task.status.clone(),
+            progress: task.progress,
+            labels: task.labels.clone(),
+            tags: task.tags.clone(),
+            log_entry: None,
+        }
+    }
+}
+
+// body of a request updating a task.
+// the first four fields are encoded like the tuple (u8, TaskStatus, Option<String>, Vec<u8>) of previous versions, which is still accepted by the server.
+#[derive(Decode)]
+#[cfg_attr(feature = "client", derive(Encode))]
+pub struct TaskUpdate {
+    // new progress in %, ignored if a progress count is given.
+    pub progress: u8,
+    // a different status than Active will finish the task.
+    pub status: TaskStatus,
+    pub description_result: Option<String>,
+    pub payload_result: Vec<u8>,
+    pub progress_count: Option<ProgressCount>,
+    // index of the running step and its own progress in %.
+    // the progress of the task is then derived from the steps, unless a progress count is given.
+    pub step: Option<(u32, u8)>,
+    // accept a progress lower than the current one when the server rejects progress going backwards.
+    pub force: bool,
+}
+
+impl TaskUpdate {
+    // update of the progress only.
+    pub fn progress(progress: u8) -> Self {
+        TaskUpdate {
+            progress,
+            status: TaskStatus::Active,
+            description_result: None,
+            payload_result: Vec::new(),
+            progress_count: None,
+            step: None,
+            force: false,
+        }
+    }
+    // update of the running step.
+    pub fn step(index: u32, progress: u8) -> Self {
+        TaskUpdate {
+            step: Some((index, progress)),
+            ..Self::progress(0)
+        }
+    }
+    // update of the progress with a count of units.
+    pub fn progress_count(current: u64, total: u64, unit: String) -> Self {
+        let count = ProgressCount {
+            current,
+            total,
+            unit,
+        };
+        TaskUpdate {
+            progress: count.percentage(),
+            progress_count: Some(count),
+            ..Self::progress(0)
+        }
+    }
+    // finish the task with the given status.
+    pub fn finish(
+        status: TaskStatus,
+        description_result: Option<String>,
+        payload_result: Vec<u8>,
+    ) -> Self {
+        TaskUpdate {
+            progress: if status == TaskStatus::Done { 100 } else { 0 },
+            status,
+            description_result,
+            payload_result,
+            progress_count: None,
+            step: None,
+            force: false,
+        }
+    }
+}
+
+// filter of the tasks to abort in bulk by an admin.
+// a task must match every filter given, only unfinished tasks can be aborted.
+#[derive(Decode)]
+#[cfg_attr(feature = "client", derive(Encode))]
+pub struct AbortFilter {
+    pub scope: Option<String>,
+    // label selector, for example `env=prod,team!=billing`.
+    pub labels: Option<String>,
+    // minimum age of the tasks in seconds since their creation.
+    pub older_than: Option<u64>,
+    pub status: Option<TaskStatus>,
+    // only return the ids of the matching tasks without aborting them.
+    pub dry_run: bool,
+    // description of the result set on the aborted tasks.
+    pub description_result: Option<String>,
+}
+
+// ids of the tasks of a bulk abort, none is aborted in dry run mode.
+#[derive(Encode)]
+#[cfg_attr(feature = "client", derive(Decode))]
+pub struct AbortResult {
+    #[bincode(with_serde)]
+    pub matched: Vec<Uuid>,
+    #[bincode(with_serde)]
+    pub aborted: Vec<Uuid>,
+    // matching tasks which finished or were removed before they could be aborted.
+    #[bincode(with_serde)]
+    pub failed: Vec<Uuid>,
+}
+
+// operation of a batch request, each operation is authorized by its own token.
+#[derive(Decode)]
+#[cfg_attr(feature = "client", derive(Encode))]
+pub enum BatchOperation {
+    // create a task with the creation token.
+    Create {
+        token: String,
+        new_task: NewTask,
+        idempotency_key: Option<String>,
+    },
+    // update a task with the update token.
+    Update {
+        token: String,
+        #[bincode(with_serde)]
+        id: Uuid,
+        update: TaskUpdate,
+    },
+    // abort a task with the abort token.
+    Abort {
+        token: String,
+        #[bincode(with_serde)]
+        id: Uuid,
+        description_result: Option<String>,
+        payload_result: Vec<u8>,
+    },
+}
+
+// result of an operation of a batch request, in the same order as the operations.
+#[derive(Encode)]
+#[cfg_attr(feature = "client", derive(Decode))]
+pub struct BatchResult {
+    // http status code the operation would have returned as a single request.
+    pub status: u16,
+    // id and tokens (view, abort, update) of the task created by a successful Create operation.
+    #[bincode(with_serde)]
+    pub id: Option<Uuid>,
+    pub tokens: Option<(String, String, String)>,
+}
+
+// figures of the server computed from the tasks in memory, durations are in seconds.
+#[derive(Encode, Serialize)]
+#[cfg_attr(feature = "client", derive(Decode, serde::Deserialize))]
+pub struct Stats {
+    pub tasks: u64,
+    // number of tasks by status name.
+    pub by_status: BTreeMap<String, u64>,
+    pub by_scope: BTreeMap<String, ScopeStats>,
+    // time from creation to completion of the finished tasks.
+    pub average_duration: Option<f64>,
+    pub duration_p50: Option<u64>,
+    pub duration_p90: Option<u64>,
+    pub duration_p99: Option<u64>,
+    // active task created the earliest, with its creation time.
+    #[bincode(with_serde)]
+    pub oldest_active: Option<(Uuid, u64)>,
+    // rough estimation in bytes of the memory used by the tasks.
+    pub memory_estimate: u64,
+}
+
+// figures of the tasks of a scope.
+#[derive(Default, Encode, Serialize)]
+#[cfg_attr(feature = "client", derive(Decode, serde::Deserialize))]
+pub struct ScopeStats {
+    pub by_status: BTreeMap<String, u64>,
+    // aborted tasks over finished tasks, None if no task of the scope is finished.
+    pub abort_ratio: Option<f64>,
 }
 
 impl NewTask {
     #[allow(clippy::wrong_self_convention)]
     pub fn to_task(self) -> Task {
+        let now = unix_timestamp();
         Task {
             id: Uuid::new_v4(),
             duration: self.duration,
@@ -96,6 +534,215 @@ impl NewTask {
             push_address: self.push_address,
             payload_result: Vec::new(),
             description_result: String::new(),
+            dependencies: self.dependencies,
+            created_at: now,
+            started_at: Some(now),
+            updated_at: now,
+            revision: 0,
+            finished_at: None,
+            progress_history: vec![(now, 0)],
+            estimated_completion: None,
+            log: Vec::new(),
+            steps: self
+                .steps
+                .into_iter()
+                .map(|name| Step {
+                    name,
+                    status: StepStatus::Pending,
+                    progress: 0,
+                })
+                .collect(),
+            current_step: None,
+            labels: self.labels,
+            tags: self.tags,
+            external_ref: self.external_ref,
+            pinned: false,
+            expires_at: self
+                .max_lifetime
+                .map(|lifetime| now.saturating_add(lifetime.into())),
+            progress_count: None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // active task with the given progress history.
+    fn task(history: &[(u64, u8)]) -> Task {
+        let mut task = NewTask::default().to_task();
+        task.progress_history = history.to_vec();
+        task
+    }
+
+    // fields of the Task of the first version, decoded by the clients of this version.
+    #[derive(Decode)]
+    struct LegacyTask {
+        #[bincode(with_serde)]
+        id: Uuid,
+        duration: u32,
+        scope: String,
+        name: String,
+        description: String,
+        payload: Vec<u8>,
+        progress: u8,
+        status: TaskStatus,
+        tokens: (String, String, String),
+        #[bincode(with_serde)]
+        push_address: Vec<Url>,
+        description_result: String,
+        payload_result: Vec<u8>,
+    }
+
+    #[test]
+    fn task_is_decoded_by_the_legacy_layout() {
+        let mut task = NewTask {
+            duration: 60,
+            scope: "scope".to_string(),
+            name: "name".to_string(),
+            description: "description".to_string(),
+            push_address: vec![Url::parse("http://127.0.0.1:9000/notify").unwrap()],
+            payload: vec![1, 2, 3],
+            ..NewTask::default()
+        }
+        .to_task();
+        task.progress = 40;
+        task.status = TaskStatus::Done;
+        task.progress_count = Some(ProgressCount {
+            current: 2,
+            total: 5,
+            unit: "files".to_string(),
+        });
+        task.description_result = "result".to_string();
+        task.payload_result = vec![4, 5];
+        let body = bincode::encode_to_vec(&task, BINCODE_CONFIG).unwrap();
+        let (legacy, _) =
+            bincode::decode_from_slice::<LegacyTask, _>(&body, BINCODE_CONFIG).unwrap();
+        assert_eq!(legacy.id, task.id);
+        assert_eq!(legacy.duration, 60);
+        assert_eq!(legacy.scope, "scope");
+        assert_eq!(legacy.name, "name");
+        assert_eq!(legacy.description, "description");
+        assert_eq!(legacy.payload, vec![1, 2, 3]);
+        assert_eq!(legacy.progress, 40);
+        assert!(legacy.status == TaskStatus::Done);
+        assert_eq!(legacy.tokens, task.tokens);
+        assert_eq!(legacy.push_address, task.push_address);
+        assert_eq!(legacy.description_result, "result");
+        assert_eq!(legacy.payload_result, vec![4, 5]);
+    }
+
+    #[test]
+    fn record_progress_bounds_the_history() {
+        let mut task = task(&[]);
+        for i in 0..PROGRESS_HISTORY_LEN as u64 + 6 {
+            task.record_progress(i as u8, 1000 + i);
+        }
+        assert_eq!(task.progress_history.len(), PROGRESS_HISTORY_LEN);
+        assert_eq!(task.progress_history.first(), Some(&(1006, 6)));
+        assert_eq!(
+            task.progress_history.last(),
+            Some(&(
+                1000 + PROGRESS_HISTORY_LEN as u64 + 5,
+                PROGRESS_HISTORY_LEN as u8 + 5
+            ))
+        );
+        assert_eq!(task.progress, PROGRESS_HISTORY_LEN as u8 + 5);
+        assert_eq!(task.updated_at, 1000 + PROGRESS_HISTORY_LEN as u64 + 5);
+    }
+
+    fn task_with_steps(count: usize) -> Task {
+        NewTask {
+            steps: (0..count).map(|i| format!("step {}", i)).collect(),
+            ..NewTask::default()
+        }
+        .to_task()
+    }
+
+    #[test]
+    fn steps_progress_is_derived_from_the_running_step() {
+        let task = task_with_steps(4);
+        assert_eq!(task.steps_progress(0, 0), Some(0));
+        assert_eq!(task.steps_progress(0, 100), Some(25));
+        assert_eq!(task.steps_progress(1, 50), Some(37));
+        assert_eq!(task.steps_progress(3, 100), Some(100));
+        let task = task_with_steps(3);
+        assert_eq!(task.steps_progress(1, 50), Some(50));
+        assert_eq!(task.steps_progress(2, 0), Some(66));
+    }
+
+    #[test]
+    fn steps_progress_clamps_the_progress_of_the_step() {
+        let task = task_with_steps(2);
+        assert_eq!(task.steps_progress(0, 250), Some(50));
+        assert_eq!(task.steps_progress(1, 101), Some(100));
+    }
+
+    #[test]
+    fn steps_progress_rejects_an_index_out_of_bounds() {
+        assert_eq!(task_with_steps(2).steps_progress(2, 0), None);
+        assert_eq!(task_with_steps(0).steps_progress(0, 0), None);
+    }
+
+    #[test]
+    fn set_current_step_updates_the_steps() {
+        let mut task = task_with_steps(3);
+        task.set_current_step(1, 200);
+        assert_eq!(task.current_step, Some(1));
+        let steps = task
+            .steps
+            .iter()
+            .map(|step| (step.status.clone(), step.progress))
+            .collect::<Vec<_>>();
+        assert!(
+            steps
+                == vec![
+                    (StepStatus::Done, 100),
+                    (StepStatus::Running, 100),
+                    (StepStatus::Pending, 0)
+                ]
+        );
+        // an index out of bounds leaves the steps unchanged.
+        task.set_current_step(3, 10);
+        assert_eq!(task.current_step, Some(1));
+        assert!(task.steps[1].status == StepStatus::Running);
+    }
+
+    #[test]
+    fn estimate_completion_extrapolates_the_throughput() {
+        assert_eq!(
+            task(&[(1000, 0), (1010, 50)]).estimate_completion(),
+            Some(1020)
+        );
+        assert_eq!(
+            task(&[(1000, 20), (1005, 30), (1010, 60)]).estimate_completion(),
+            Some(1020)
+        );
+    }
+
+    #[test]
+    fn estimate_completion_rounds_up() {
+        // 70% remaining at 30% per 7 seconds is 16.3 seconds.
+        assert_eq!(task(&[(0, 0), (7, 30)]).estimate_completion(), Some(24));
+    }
+
+    #[test]
+    fn estimate_completion_needs_the_progress_to_advance() {
+        assert_eq!(task(&[]).estimate_completion(), None);
+        assert_eq!(task(&[(1000, 10)]).estimate_completion(), None);
+        assert_eq!(task(&[(1000, 10), (1010, 10)]).estimate_completion(), None);
+        assert_eq!(task(&[(1000, 50), (1010, 40)]).estimate_completion(), None);
+        // progress given twice in the same second.
+        assert_eq!(task(&[(1000, 10), (1000, 20)]).estimate_completion(), None);
+    }
+
+    #[test]
+    fn estimate_completion_only_for_active_tasks() {
+        for status in [TaskStatus::Blocked, TaskStatus::Done, TaskStatus::Aborted] {
+            let mut task = task(&[(1000, 0), (1010, 50)]);
+            task.status = status;
+            assert_eq!(task.estimate_completion(), None);
         }
     }
 }